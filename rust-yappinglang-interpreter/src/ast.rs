@@ -1,3 +1,6 @@
+use crate::bigint::BigInt;
+use crate::rational::Rational;
+
 #[derive(Debug)]
 pub struct Block {
     pub exp: Option<Box<Exp>>,
@@ -8,11 +11,14 @@ pub enum ExpData {
     CapturedVar(String),
     Block(Block),
     Integer(i64),
+    BigInteger(BigInt),
+    Rational(Rational),
     Decimal(f64),
     String(String),
 }
 #[derive(Debug)]
 pub struct Exp {
     pub data: ExpData,
+    pub line: u64,
     pub next_exp: Option<Box<Exp>>,
 }
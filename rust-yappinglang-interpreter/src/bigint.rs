@@ -0,0 +1,457 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+/// Integer of any size as sign and magnitude.
+/// Digits are base 2^32, least significant first, without leading zeros, so zero has none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        BigInt::new(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl BigInt {
+    fn new(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BigInt {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    /// Decimal digits with an optional `-`.
+    pub fn parse(str: &str) -> Option<Self> {
+        let (negative, digits) = match str.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, str),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let mut magnitude = vec![];
+        for chunk in digits.as_bytes().chunks(9) {
+            let value = std::str::from_utf8(chunk).ok()?.parse::<u32>().ok()?;
+            magnitude = mul_small(&magnitude, 10u32.pow(chunk.len() as u32), value);
+        }
+        Some(BigInt::new(negative, magnitude))
+    }
+
+    /// Whole part of a finite `value`.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let value = value.trunc();
+        let bits = value.abs().to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i32;
+        if exponent == 0 {
+            // Zero or subnormal, both truncate to zero.
+            return Some(BigInt::new(false, vec![]));
+        }
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let shift = exponent - 1075;
+        let magnitude = if shift < 0 {
+            let mantissa = mantissa >> -shift;
+            vec![mantissa as u32, (mantissa >> 32) as u32]
+        } else {
+            let mut digits = vec![0; shift as usize / 32];
+            digits.extend(shl_bits(
+                &[mantissa as u32, (mantissa >> 32) as u32],
+                shift as u32 % 32,
+            ));
+            digits
+        };
+        Some(BigInt::new(value < 0.0, magnitude))
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0u64, |acc, d| (acc << 32) | *d as u64);
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0.0, |acc, d| acc * 4294967296.0 + *d as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> Self {
+        BigInt::new(false, self.digits.clone())
+    }
+
+    /// Square and multiply, a shift for powers of two.
+    pub fn pow(&self, exponent: u32) -> Self {
+        let negative = self.negative && exponent & 1 == 1;
+        if self.count_ones() == 1 {
+            if let Ok(shift) = u32::try_from((self.bits() - 1) * exponent as u64) {
+                return BigInt::new(negative, BigInt::from(1).shl(shift).digits);
+            }
+        }
+        let mut result = vec![1];
+        let mut square = self.digits.clone();
+        let mut rest = exponent;
+        while rest > 0 {
+            if rest & 1 == 1 {
+                result = mul_mag(&result, &square);
+            }
+            rest >>= 1;
+            if rest > 0 {
+                square = mul_mag(&square, &square);
+            }
+        }
+        BigInt::new(negative, result)
+    }
+
+    /// Magnitude times 2^bits, the sign is kept.
+    pub fn shl(&self, bits: u32) -> Self {
+        if self.is_zero() {
+            return self.clone();
+        }
+        let mut digits = vec![0; (bits / 32) as usize];
+        digits.extend(shl_bits(&self.digits, bits % 32));
+        BigInt::new(self.negative, digits)
+    }
+
+    /// Divided by 2^bits rounding down, like `>>` on `i64`.
+    pub fn shr(&self, bits: u32) -> Self {
+        if bits as u64 >= self.bits() {
+            return BigInt::from(if self.negative { -1 } else { 0 });
+        }
+        let (whole, rest) = ((bits / 32) as usize, bits % 32);
+        let quotient = BigInt::new(false, shr_bits(&self.digits[whole..], rest));
+        if !self.negative {
+            return quotient;
+        }
+        // Rounding down a negative quotient adds one to the magnitude when bits are dropped.
+        let dropped = self.digits[..whole].iter().any(|d| *d != 0)
+            || self.digits[whole] & ((1 << rest) - 1) != 0;
+        let magnitude = if dropped {
+            add_mag(&quotient.digits, &[1])
+        } else {
+            quotient.digits
+        };
+        BigInt::new(true, magnitude)
+    }
+
+    /// Number of bits of the magnitude, 0 for zero.
+    pub fn bits(&self) -> u64 {
+        self.digits.last().map_or(0, |d| {
+            self.digits.len() as u64 * 32 - d.leading_zeros() as u64
+        })
+    }
+
+    /// Number of one bits of the magnitude.
+    pub fn count_ones(&self) -> u64 {
+        self.digits.iter().map(|d| d.count_ones() as u64).sum()
+    }
+
+    /// Non-negative, zero only for two zeros.
+    pub fn gcd(&self, other: &BigInt) -> Self {
+        let (mut a, mut b) = (self.abs(), other.abs());
+        while let Some((_, remainder)) = a.div_rem(&b) {
+            a = b;
+            b = remainder;
+        }
+        a
+    }
+
+    // The `checked_` methods have the names of the `i64` ones, so operators can use either.
+    // Only division by zero fails.
+
+    pub fn checked_neg(&self) -> Option<Self> {
+        Some(BigInt::new(!self.negative, self.digits.clone()))
+    }
+
+    pub fn checked_add(&self, other: &BigInt) -> Option<Self> {
+        if self.negative == other.negative {
+            return Some(BigInt::new(
+                self.negative,
+                add_mag(&self.digits, &other.digits),
+            ));
+        }
+        Some(match cmp_mag(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::new(other.negative, sub_mag(&other.digits, &self.digits)),
+            _ => BigInt::new(self.negative, sub_mag(&self.digits, &other.digits)),
+        })
+    }
+
+    pub fn checked_sub(&self, other: &BigInt) -> Option<Self> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    pub fn checked_mul(&self, other: &BigInt) -> Option<Self> {
+        Some(BigInt::new(
+            self.negative != other.negative,
+            mul_mag(&self.digits, &other.digits),
+        ))
+    }
+
+    /// Rounds toward zero like `i64`.
+    pub fn checked_div(&self, other: &BigInt) -> Option<Self> {
+        Some(self.div_rem(other)?.0)
+    }
+
+    /// Has the sign of `self` like `i64`.
+    pub fn checked_rem(&self, other: &BigInt) -> Option<Self> {
+        Some(self.div_rem(other)?.1)
+    }
+
+    /// Quotient rounded down and the remainder with the sign of `other`.
+    pub fn div_mod_floor(&self, other: &BigInt) -> Option<(Self, Self)> {
+        let (quotient, remainder) = self.div_rem(other)?;
+        if remainder.is_zero() || remainder.negative == other.negative {
+            return Some((quotient, remainder));
+        }
+        Some((
+            quotient.checked_sub(&BigInt::from(1))?,
+            remainder.checked_add(other)?,
+        ))
+    }
+
+    /// Quotient and remainder of `checked_div` and `checked_rem`.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_mag(&self.digits, &other.digits);
+        Some((
+            BigInt::new(self.negative != other.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.digits, &other.digits),
+            (true, true) => cmp_mag(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        let mut chunks = vec![];
+        let mut magnitude = self.digits.clone();
+        while !magnitude.is_empty() {
+            let (quotient, chunk) = div_rem_small(&magnitude, 1_000_000_000);
+            chunks.push(chunk);
+            magnitude = quotient;
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            f.write_fmt(format_args!("{first}"))?;
+        }
+        for chunk in chunks {
+            f.write_fmt(format_args!("{chunk:09}"))?;
+        }
+        Ok(())
+    }
+}
+
+fn trim(mut digits: Vec<u32>) -> Vec<u32> {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    digits
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, d) in long.iter().enumerate() {
+        let s = *d as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(s as u32);
+        carry = s >> 32;
+    }
+    sum.push(carry as u32);
+    trim(sum)
+}
+
+/// `a - b` for `a >= b`.
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, d) in a.iter().enumerate() {
+        let t = *d as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        difference.push(t as u32);
+        borrow = (t < 0) as i64;
+    }
+    trim(difference)
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let t = *x as u64 * *y as u64 + product[i + j] as u64 + carry;
+            product[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    trim(product)
+}
+
+/// `a * m + add`.
+fn mul_small(a: &[u32], m: u32, add: u32) -> Vec<u32> {
+    let mut product = Vec::with_capacity(a.len() + 1);
+    let mut carry = add as u64;
+    for d in a {
+        let t = *d as u64 * m as u64 + carry;
+        product.push(t as u32);
+        carry = t >> 32;
+    }
+    product.push(carry as u32);
+    trim(product)
+}
+
+fn div_rem_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0; a.len()];
+    let mut remainder = 0u64;
+    for i in (0..a.len()).rev() {
+        let t = (remainder << 32) | a[i] as u64;
+        quotient[i] = (t / d as u64) as u32;
+        remainder = t % d as u64;
+    }
+    (trim(quotient), remainder as u32)
+}
+
+/// `a << shift` for `shift < 32`, one digit longer than `a`.
+fn shl_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    let mut shifted = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for d in a {
+        shifted.push((d << shift) | carry);
+        carry = if shift == 0 { 0 } else { d >> (32 - shift) };
+    }
+    shifted.push(carry);
+    shifted
+}
+
+fn shr_bits(a: &[u32], shift: u32) -> Vec<u32> {
+    let mut shifted = Vec::with_capacity(a.len());
+    for i in 0..a.len() {
+        let high = if shift == 0 {
+            0
+        } else {
+            a.get(i + 1).map_or(0, |d| d << (32 - shift))
+        };
+        shifted.push((a[i] >> shift) | high);
+    }
+    trim(shifted)
+}
+
+/// Long division, algorithm D of Knuth's TAOCP 4.3.1.
+fn div_rem_mag(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_mag(u, v) == Ordering::Less {
+        return (vec![], u.to_vec());
+    }
+    if v.len() == 1 {
+        let (quotient, remainder) = div_rem_small(u, v[0]);
+        return (quotient, trim(vec![remainder]));
+    }
+    // Normalize so the top digit of the divisor has its high bit set,
+    // then each estimated quotient digit is at most 2 too large.
+    let shift = v[v.len() - 1].leading_zeros();
+    let mut v = shl_bits(v, shift);
+    v.pop();
+    let mut u = shl_bits(u, shift);
+    let n = v.len();
+    let m = u.len() - n - 1;
+    let base = 1u64 << 32;
+    let mut quotient = vec![0u32; m + 1];
+    for j in (0..=m).rev() {
+        let top = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut qhat = top / v[n - 1] as u64;
+        let mut rhat = top % v[n - 1] as u64;
+        while qhat >= base || qhat * v[n - 2] as u64 > ((rhat << 32) | u[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += v[n - 1] as u64;
+            if rhat >= base {
+                break;
+            }
+        }
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let p = qhat * v[i] as u64 + carry;
+            carry = p >> 32;
+            let t = u[i + j] as i64 - borrow - (p & 0xffff_ffff) as i64;
+            u[i + j] = t as u32;
+            borrow = (t < 0) as i64;
+        }
+        let t = u[j + n] as i64 - borrow - carry as i64;
+        u[j + n] = t as u32;
+        if t < 0 {
+            // Estimate was one too large, add the divisor back.
+            qhat -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let s = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = s as u32;
+                carry = s >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = qhat as u32;
+    }
+    (trim(quotient), shr_bits(&u[..n], shift))
+}
@@ -0,0 +1,499 @@
+use crate::interpreter::data::Data;
+use crate::interpreter::debugger::{Breakpoints, Debugger, Frontend, Pause, PauseReason, Resume};
+use crate::interpreter::Interpreter;
+use crate::json::{json_object, Json};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::utils::ResultToString;
+use std::cell::Cell;
+use std::io::{stdin, stdout, BufRead, LineWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+
+const THREAD_ID: i64 = 1;
+/// Scopes of frame `n` get variable references `n * SCOPES + scope + 1`.
+const SCOPES: i64 = 3;
+
+/// Debug Adapter Protocol connection over stdin and stdout.
+struct Connection {
+    seq: Cell<i64>,
+    incoming: Receiver<Json>,
+}
+
+impl Connection {
+    fn new() -> Self {
+        let (sender, incoming) = channel();
+        std::thread::spawn(move || {
+            let mut input = stdin().lock();
+            while let Ok(Some(message)) = read_message(&mut input) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            seq: Cell::new(1),
+            incoming,
+        }
+    }
+
+    fn send(&self, mut message: Json) -> Result<(), String> {
+        if let Json::Object(fields) = &mut message {
+            fields.insert(0, ("seq".to_string(), Json::from(self.seq.get())));
+        }
+        self.seq.set(self.seq.get() + 1);
+        let body = message.to_string();
+        let mut out = stdout().lock();
+        write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body).str_res()?;
+        out.flush().str_res()
+    }
+
+    fn event(&self, event: &str, body: Json) -> Result<(), String> {
+        self.send(json_object! {
+            "type" => "event",
+            "event" => event,
+            "body" => body,
+        })
+    }
+
+    fn respond(&self, request: &Json, body: Result<Json, String>) -> Result<(), String> {
+        let seq = request.get("seq").and_then(Json::as_i64).unwrap_or(0);
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let mut response = json_object! {
+            "type" => "response",
+            "request_seq" => seq,
+            "command" => command,
+            "success" => body.is_ok(),
+        };
+        if let Json::Object(fields) = &mut response {
+            match body {
+                Ok(body) => fields.push(("body".to_string(), body)),
+                Err(message) => fields.push(("message".to_string(), Json::from(message))),
+            }
+        }
+        self.send(response)
+    }
+
+    /// Blocks until the next request, `None` when the client is gone.
+    fn next(&self) -> Option<Json> {
+        self.incoming.recv().ok()
+    }
+}
+
+fn read_message(input: &mut impl BufRead) -> Result<Option<Json>, String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).str_res()? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().str_res()?);
+        }
+    }
+    let Some(length) = length else {
+        return Err("dap: message without Content-Length".to_string());
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).str_res()?;
+    Json::parse(&String::from_utf8_lossy(&body)).map(Some)
+}
+
+/// Sends program output to the client as `output` events.
+struct OutputEvents(Rc<Connection>);
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .event(
+                "output",
+                json_object! {
+                    "category" => "stdout",
+                    "output" => String::from_utf8_lossy(buf).to_string(),
+                },
+            )
+            .map_err(std::io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct DapFrontend {
+    conn: Rc<Connection>,
+}
+
+impl DapFrontend {
+    fn set_breakpoints(&self, request: &Json, breakpoints: &mut Breakpoints) -> Json {
+        let args = request.get("arguments");
+        let lines: Vec<u64> = args
+            .and_then(|a| a.get("breakpoints"))
+            .and_then(Json::as_array)
+            .map(|b| {
+                b.iter()
+                    .filter_map(|b| b.get("line").and_then(Json::as_i64))
+                    .map(|l| l as u64)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let source = args
+            .and_then(|a| a.get("source"))
+            .and_then(|s| s.get("path"))
+            .and_then(Json::as_str)
+            .and_then(Breakpoints::source);
+        let found = source.is_some();
+        if let Some(source) = source {
+            if lines.is_empty() {
+                breakpoints.lines.remove(&source);
+            } else {
+                breakpoints
+                    .lines
+                    .insert(source, lines.iter().copied().collect());
+            }
+        }
+        let verified: Vec<Json> = lines
+            .iter()
+            .map(|l| match found {
+                true => json_object! { "verified" => true, "line" => *l },
+                false => json_object! {
+                    "verified" => false,
+                    "line" => *l,
+                    "message" => "source not found",
+                },
+            })
+            .collect();
+        json_object! { "breakpoints" => verified }
+    }
+
+    fn set_function_breakpoints(&self, request: &Json, breakpoints: &mut Breakpoints) -> Json {
+        let names: Vec<String> = request
+            .get("arguments")
+            .and_then(|a| a.get("breakpoints"))
+            .and_then(Json::as_array)
+            .map(|b| {
+                b.iter()
+                    .filter_map(|b| b.get("name").and_then(Json::as_str))
+                    .map(|n| n.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        breakpoints.functions = names.iter().cloned().collect();
+        let verified: Vec<Json> = names
+            .iter()
+            .map(|_| json_object! { "verified" => true })
+            .collect();
+        json_object! { "breakpoints" => verified }
+    }
+
+    fn stack_trace(&self, pause: &Pause) -> Json {
+        let frames: Vec<Json> = pause
+            .frames
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(id, frame)| {
+                json_object! {
+                    "id" => id,
+                    "name" => frame.name.clone(),
+                    "line" => frame.line,
+                    "column" => 1i64,
+                    "source" => json_object! { "path" => frame.file.to_string() },
+                }
+            })
+            .collect();
+        json_object! {
+            "stackFrames" => frames,
+            "totalFrames" => pause.frames.len(),
+        }
+    }
+
+    fn scopes(&self, request: &Json) -> Json {
+        let frame = arg(request, "frameId").unwrap_or(0);
+        let scopes: Vec<Json> = ["Stack", "Locals", "Captured"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                json_object! {
+                    "name" => *name,
+                    "variablesReference" => frame * SCOPES + i as i64 + 1,
+                    "expensive" => false,
+                }
+            })
+            .collect();
+        json_object! { "scopes" => scopes }
+    }
+
+    fn variables(&self, request: &Json, pause: &Pause) -> Result<Json, String> {
+        let reference = arg(request, "variablesReference").unwrap_or(0);
+        let frame = usize::try_from(reference - 1)
+            .ok()
+            .and_then(|i| pause.frames.iter().rev().nth(i / SCOPES as usize));
+        let Some(frame) = frame else {
+            return Err(format!("no frame for reference {reference}"));
+        };
+        let reference = reference - 1;
+        let vars: Vec<(String, &Data)> = match reference % SCOPES {
+            0 => pause
+                .stack
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, d)| (i.to_string(), d))
+                .collect(),
+            1 => frame
+                .locals()
+                .into_iter()
+                .map(|(k, v)| (k.clone(), v))
+                .collect(),
+            _ => frame
+                .captured()
+                .into_iter()
+                .map(|(k, v)| (k.clone(), v))
+                .collect(),
+        };
+        let vars: Vec<Json> = vars
+            .into_iter()
+            .map(|(name, data)| {
+                json_object! {
+                    "name" => name,
+                    "value" => data.to_string(),
+                    "variablesReference" => 0i64,
+                }
+            })
+            .collect();
+        Ok(json_object! { "variables" => vars })
+    }
+
+    fn evaluate(&self, request: &Json, pause: &Pause) -> Result<Json, String> {
+        let expression = request
+            .get("arguments")
+            .and_then(|a| a.get("expression"))
+            .and_then(Json::as_str)
+            .unwrap_or("");
+        let frame = arg(request, "frameId").unwrap_or(0) as usize;
+        if frame >= pause.frames.len() {
+            return Err(format!("no frame {frame}"));
+        }
+        let stack = pause.evaluate(pause.frames.len() - 1 - frame, expression)?;
+        let result = match stack.last() {
+            Some(data) => data.to_string(),
+            None => "empty stack".to_string(),
+        };
+        Ok(json_object! { "result" => result, "variablesReference" => 0i64 })
+    }
+}
+
+impl Frontend for DapFrontend {
+    fn paused(&mut self, pause: &mut Pause) -> Result<Resume, String> {
+        let reason = match pause.reason {
+            PauseReason::Entry => "entry",
+            PauseReason::Step => "step",
+            PauseReason::Breakpoint(_) => "breakpoint",
+            PauseReason::Function(_) => "function breakpoint",
+            PauseReason::Interrupt => "pause",
+        };
+        self.conn.event(
+            "stopped",
+            json_object! {
+                "reason" => reason,
+                "threadId" => THREAD_ID,
+                "allThreadsStopped" => true,
+            },
+        )?;
+        loop {
+            let Some(request) = self.conn.next() else {
+                return Err("debugger: client disconnected".to_string());
+            };
+            let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+            let resume = match command {
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::StepOver),
+                "stepIn" => Some(Resume::StepIn),
+                "stepOut" => Some(Resume::StepOut),
+                _ => None,
+            };
+            if let Some(resume) = resume {
+                let body = if command == "continue" {
+                    json_object! { "allThreadsContinued" => true }
+                } else {
+                    json_object! {}
+                };
+                self.conn.respond(&request, Ok(body))?;
+                return Ok(resume);
+            }
+            let body = match command {
+                "threads" => Ok(threads()),
+                "stackTrace" => Ok(self.stack_trace(pause)),
+                "scopes" => Ok(self.scopes(&request)),
+                "variables" => self.variables(&request, pause),
+                "evaluate" => self.evaluate(&request, pause),
+                "setBreakpoints" => Ok(self.set_breakpoints(&request, pause.breakpoints)),
+                "setFunctionBreakpoints" => {
+                    Ok(self.set_function_breakpoints(&request, pause.breakpoints))
+                }
+                "disconnect" | "terminate" => {
+                    self.conn.respond(&request, Ok(json_object! {}))?;
+                    return Err("debugger: client disconnected".to_string());
+                }
+                _ => Err(format!("unsupported request '{command}'")),
+            };
+            self.conn.respond(&request, body)?;
+        }
+    }
+
+    fn interrupt(&mut self, breakpoints: &mut Breakpoints) -> Result<bool, String> {
+        loop {
+            let request = match self.conn.incoming.try_recv() {
+                Ok(request) => request,
+                Err(TryRecvError::Empty) => return Ok(false),
+                Err(TryRecvError::Disconnected) => {
+                    return Err("debugger: client disconnected".to_string())
+                }
+            };
+            let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+            let body = match command {
+                "pause" => {
+                    self.conn.respond(&request, Ok(json_object! {}))?;
+                    return Ok(true);
+                }
+                "threads" => Ok(threads()),
+                "setBreakpoints" => Ok(self.set_breakpoints(&request, breakpoints)),
+                "setFunctionBreakpoints" => {
+                    Ok(self.set_function_breakpoints(&request, breakpoints))
+                }
+                "disconnect" | "terminate" => {
+                    self.conn.respond(&request, Ok(json_object! {}))?;
+                    return Err("debugger: client disconnected".to_string());
+                }
+                _ => Err(format!("'{command}' is not available while running")),
+            };
+            self.conn.respond(&request, body)?;
+        }
+    }
+}
+
+fn threads() -> Json {
+    json_object! {
+        "threads" => vec![json_object! { "id" => THREAD_ID, "name" => "main" }],
+    }
+}
+
+fn arg(request: &Json, name: &str) -> Option<i64> {
+    request
+        .get("arguments")
+        .and_then(|a| a.get(name))
+        .and_then(Json::as_i64)
+}
+
+/// Serves one debug session on stdin and stdout.
+///
+/// Breakpoints and other configuration are collected until `configurationDone`,
+/// then the program given in `launch` runs with its output sent as events.
+pub fn run_dap() -> Result<(), String> {
+    let conn = Rc::new(Connection::new());
+    let mut breakpoints = Breakpoints::default();
+    let mut launch = None;
+    let mut configured = false;
+    while launch.is_none() || !configured {
+        let Some(request) = conn.next() else {
+            return Ok(());
+        };
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let frontend = DapFrontend { conn: conn.clone() };
+        let body = match command {
+            "initialize" => Ok(json_object! {
+                "supportsConfigurationDoneRequest" => true,
+                "supportsFunctionBreakpoints" => true,
+                "supportsEvaluateForHovers" => true,
+            }),
+            "setBreakpoints" => Ok(frontend.set_breakpoints(&request, &mut breakpoints)),
+            "setFunctionBreakpoints" => {
+                Ok(frontend.set_function_breakpoints(&request, &mut breakpoints))
+            }
+            "configurationDone" => {
+                configured = true;
+                Ok(json_object! {})
+            }
+            "launch" => {
+                launch = request.get("arguments").cloned();
+                Ok(json_object! {})
+            }
+            "threads" => Ok(threads()),
+            "disconnect" | "terminate" => {
+                conn.respond(&request, Ok(json_object! {}))?;
+                return Ok(());
+            }
+            _ => Err(format!("unsupported request '{command}'")),
+        };
+        conn.respond(&request, body)?;
+        if command == "initialize" {
+            conn.event("initialized", json_object! {})?;
+        }
+    }
+    let launch = launch.unwrap_or(Json::Null);
+    let program = launch
+        .get("program")
+        .and_then(Json::as_str)
+        .unwrap_or("")
+        .to_string();
+    let stop_on_entry = launch
+        .get("stopOnEntry")
+        .and_then(Json::as_bool)
+        .unwrap_or(false);
+    let no_debug = launch
+        .get("noDebug")
+        .and_then(Json::as_bool)
+        .unwrap_or(false);
+
+    let result = run_program(&conn, &program, breakpoints, stop_on_entry, no_debug);
+    if let Err(e) = &result {
+        conn.event(
+            "output",
+            json_object! { "category" => "stderr", "output" => format!("error:\n{e}\n") },
+        )?;
+    }
+    conn.event(
+        "exited",
+        json_object! { "exitCode" => if result.is_ok() { 0i64 } else { 1 } },
+    )?;
+    conn.event("terminated", json_object! {})?;
+    while let Some(request) = conn.next() {
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        if command == "disconnect" || command == "terminate" {
+            conn.respond(&request, Ok(json_object! {}))?;
+            break;
+        }
+        conn.respond(&request, Err("program has ended".to_string()))?;
+    }
+    Ok(())
+}
+
+fn run_program(
+    conn: &Rc<Connection>,
+    program: &str,
+    breakpoints: Breakpoints,
+    stop_on_entry: bool,
+    no_debug: bool,
+) -> Result<(), String> {
+    let source = std::fs::read_to_string(program).str_res()?;
+    let tokens = Scanner::new(&source).scan_tokens()?;
+    let ast = Parser::new(tokens).build_tree()?;
+    let mut intr = Interpreter::new(crate::get_std_defs());
+    crate::project::configure_for(&mut intr, Path::new(program))?;
+    intr.set_output(Box::new(LineWriter::new(OutputEvents(conn.clone()))));
+    if !no_debug {
+        let mut debugger = Debugger::new(Box::new(DapFrontend { conn: conn.clone() }));
+        debugger.breakpoints = breakpoints;
+        debugger.set_stop_on_entry(stop_on_entry);
+        intr.set_debugger(debugger);
+    }
+    let block = intr.load(&ast, program);
+    intr.run(block).str_res()
+}
@@ -0,0 +1,140 @@
+use crate::interpreter::debugger::{Breakpoints, Frontend, Pause, PauseReason, Resume};
+use crate::utils::ResultToString;
+use std::io::{stdin, stdout, Write};
+
+const HELP: &str = "\
+c, continue       run until next breakpoint
+s, step           step into Fn calls and Do blocks
+n, next           step over
+o, out            step out of current block
+b <line|Name>     set breakpoint on line of current file or Def-fn name
+d <line|Name>     delete breakpoint
+bl                list breakpoints
+bt                show frames
+stack             show data stack, top first
+defs [frame]      show local defs of frame (0 is current)
+captured [frame]  show captured vars of frame
+p <code>          evaluate code in current frame and show the stack top
+l                 show current source line
+q, quit           stop program";
+
+/// Debugger frontend reading commands from stdin.
+pub struct DebugCli {
+    source: Vec<String>,
+}
+
+impl DebugCli {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.lines().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    fn show_line(&self, line: u64) {
+        if let Some(text) = self.source.get((line as usize).wrapping_sub(1)) {
+            println!("{line:>4} | {text}");
+        }
+    }
+}
+
+impl Frontend for DebugCli {
+    fn paused(&mut self, pause: &mut Pause) -> Result<Resume, String> {
+        match &pause.reason {
+            PauseReason::Entry => println!("paused on entry"),
+            PauseReason::Step => {}
+            PauseReason::Breakpoint(line) => println!("breakpoint at line {line}"),
+            PauseReason::Function(name) => println!("breakpoint in {name}"),
+            PauseReason::Interrupt => println!("paused"),
+        }
+        self.show_line(pause.line);
+        loop {
+            print!("(debug) ");
+            stdout().flush().str_res()?;
+            let mut input = String::new();
+            if stdin().read_line(&mut input).str_res()? == 0 {
+                return Ok(Resume::Continue);
+            }
+            let (cmd, arg) = match input.trim().split_once(' ') {
+                Some((cmd, arg)) => (cmd, arg.trim()),
+                None => (input.trim(), ""),
+            };
+            match cmd {
+                "c" | "continue" => return Ok(Resume::Continue),
+                "s" | "step" => return Ok(Resume::StepIn),
+                "n" | "next" => return Ok(Resume::StepOver),
+                "o" | "out" => return Ok(Resume::StepOut),
+                "q" | "quit" => return Err("debugger: program stopped".to_string()),
+                "b" | "d" => {
+                    if arg.is_empty() {
+                        println!("expect line or function name");
+                        continue;
+                    }
+                    let set = cmd == "b";
+                    if let Ok(line) = arg.parse::<u64>() {
+                        let file = &pause.frames[pause.frames.len() - 1].file;
+                        let Some(source) = Breakpoints::source(file) else {
+                            println!("no file for line breakpoints");
+                            continue;
+                        };
+                        let lines = pause.breakpoints.lines.entry(source).or_default();
+                        if set {
+                            lines.insert(line);
+                        } else {
+                            lines.remove(&line);
+                        }
+                    } else if set {
+                        pause.breakpoints.functions.insert(arg.to_string());
+                    } else {
+                        pause.breakpoints.functions.remove(arg);
+                    }
+                }
+                "bl" => {
+                    for (source, lines) in &pause.breakpoints.lines {
+                        lines
+                            .iter()
+                            .for_each(|l| println!("line {}:{l}", source.display()));
+                    }
+                    pause
+                        .breakpoints
+                        .functions
+                        .iter()
+                        .for_each(|f| println!("fn {f}"));
+                }
+                "bt" => {
+                    for (i, frame) in pause.frames.iter().rev().enumerate() {
+                        println!("#{i} {} line {}", frame.name, frame.line);
+                    }
+                }
+                "stack" => pause.stack.iter().rev().for_each(|d| println!("{d}")),
+                "defs" | "captured" => {
+                    let Some(frame) = arg
+                        .parse::<usize>()
+                        .ok()
+                        .or(arg.is_empty().then_some(0))
+                        .and_then(|i| pause.frames.iter().rev().nth(i))
+                    else {
+                        println!("no such frame");
+                        continue;
+                    };
+                    let defs = if cmd == "defs" {
+                        frame.locals()
+                    } else {
+                        frame.captured()
+                    };
+                    defs.iter().for_each(|(k, v)| println!("{k} = {v}"));
+                }
+                "p" => match pause.evaluate(pause.frames.len() - 1, arg) {
+                    Ok(stack) => match stack.last() {
+                        Some(data) => println!("{data}"),
+                        None => println!("empty stack"),
+                    },
+                    Err(e) => println!("error:\n{e}"),
+                },
+                "l" => self.show_line(pause.line),
+                "h" | "help" => println!("{HELP}"),
+                "" => {}
+                _ => println!("unknown command '{cmd}', h for help"),
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use crate::interpreter::run_tree;
+use crate::json::Json;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::{Token, TokenData};
+use crate::{ast, interpreter};
+use std::fmt::Write;
+use std::rc::Rc;
+
+pub const USAGE: &str = "dump tokens|ast|run-tree file.yp [sexpr|json|dot|puml] [out]";
+
+/// Tree shared by all output formats.
+pub struct Node {
+    pub kind: &'static str,
+    pub value: Option<String>,
+    pub line: Option<u64>,
+    /// `capture_vars` of a run tree block.
+    pub captures: Option<Vec<String>>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    fn new(kind: &'static str, value: Option<String>, line: Option<u64>) -> Self {
+        Self {
+            kind,
+            value,
+            line,
+            captures: None,
+            children: vec![],
+        }
+    }
+
+    fn label(&self) -> String {
+        let mut label = self.kind.to_string();
+        if let Some(value) = &self.value {
+            let _ = write!(label, " {value}");
+        }
+        if let Some(captures) = &self.captures {
+            let _ = write!(label, " [{}]", captures.join(" "));
+        }
+        if let Some(line) = self.line {
+            let _ = write!(label, " :{line}");
+        }
+        label
+    }
+}
+
+/// `dump` subcommand, `args` are the arguments after `dump`.
+pub fn run_dump(args: &[String]) -> Result<(), String> {
+    let [what, file, rest @ ..] = args else {
+        return Err(format!("usage: {USAGE}"));
+    };
+    let format = rest.first().map_or("sexpr", |f| f.as_str());
+    let source = std::fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))?;
+    let tokens = Scanner::new(&source).scan_tokens()?;
+    let node = match what.as_str() {
+        "tokens" => tokens_node(&tokens),
+        "ast" => ast_node(&Parser::new(tokens).build_tree()?),
+        "run-tree" => {
+            let ast = Parser::new(tokens).build_tree()?;
+            run_tree_node(&run_tree::load(&ast, &Rc::from(file.as_str())))
+        }
+        _ => return Err(format!("usage: {USAGE}")),
+    };
+    let text = match format {
+        "sexpr" => sexpr(&node),
+        "json" => format!("{}\n", json(&node)),
+        "dot" => dot(&node),
+        "puml" => puml(&node),
+        _ => return Err(format!("unknown format '{format}', usage: {USAGE}")),
+    };
+    match rest.get(1) {
+        Some(out) => std::fs::write(out, text).map_err(|e| format!("{out}: {e}")),
+        None => {
+            print!("{text}");
+            Ok(())
+        }
+    }
+}
+
+pub fn tokens_node(tokens: &[Token]) -> Node {
+    let mut node = Node::new("tokens", None, None);
+    for token in tokens {
+        let (kind, value) = match &token.typ {
+            TokenData::Identifier(name) => ("identifier", Some(name.clone())),
+            TokenData::String(str) => ("string", Some(format!("{str:?}"))),
+            TokenData::Integer(int) => ("integer", Some(int.to_string())),
+            TokenData::BigInteger(int) => ("integer", Some(int.to_string())),
+            TokenData::Rational(rat) => ("rational", Some(rat.to_string())),
+            TokenData::Decimal(dec) => ("decimal", Some(dec.to_string())),
+            TokenData::Semicolon => ("semicolon", None),
+            TokenData::LeftParen => ("left-paren", None),
+            TokenData::RightParen => ("right-paren", None),
+            TokenData::Capture => ("capture", None),
+        };
+        node.children.push(Node::new(kind, value, Some(token.line)));
+    }
+    node
+}
+
+/// Expressions of a block in `next_exp` order.
+pub fn ast_node(block: &ast::Block) -> Node {
+    let mut node = Node::new("block", None, None);
+    let mut exp = block.exp.as_deref();
+    while let Some(e) = exp {
+        let line = Some(e.line);
+        node.children.push(match &e.data {
+            ast::ExpData::Var(var) => Node::new("var", Some(var.clone()), line),
+            ast::ExpData::CapturedVar(var) => Node::new("capture", Some(var.clone()), line),
+            ast::ExpData::Block(block) => Node {
+                line,
+                ..ast_node(block)
+            },
+            ast::ExpData::Integer(int) => Node::new("integer", Some(int.to_string()), line),
+            ast::ExpData::BigInteger(int) => Node::new("integer", Some(int.to_string()), line),
+            ast::ExpData::Rational(rat) => Node::new("rational", Some(rat.to_string()), line),
+            ast::ExpData::Decimal(dec) => Node::new("decimal", Some(dec.to_string()), line),
+            ast::ExpData::String(str) => Node::new("string", Some(format!("{str:?}")), line),
+        });
+        exp = e.next_exp.as_deref();
+    }
+    node
+}
+
+pub fn run_tree_node(block: &interpreter::run_tree::Block) -> Node {
+    let mut captures: Vec<_> = block.capture_vars.iter().cloned().collect();
+    captures.sort();
+    let mut node = Node::new("block", None, None);
+    node.captures = Some(captures);
+    let mut exp = block.exp.as_deref();
+    while let Some(e) = exp {
+        let line = Some(e.line);
+        node.children.push(match &e.data {
+            run_tree::ExpData::Var(var) => Node::new("var", Some(var.clone()), line),
+            run_tree::ExpData::Block(block) => Node {
+                line,
+                ..run_tree_node(block)
+            },
+            run_tree::ExpData::Integer(int) => Node::new("integer", Some(int.to_string()), line),
+            run_tree::ExpData::BigInteger(int) => Node::new("integer", Some(int.to_string()), line),
+            run_tree::ExpData::Rational(rat) => Node::new("rational", Some(rat.to_string()), line),
+            run_tree::ExpData::Decimal(dec) => Node::new("decimal", Some(dec.to_string()), line),
+            run_tree::ExpData::String(str) => Node::new("string", Some(format!("{str:?}")), line),
+        });
+        exp = e.next_exp.as_deref();
+    }
+    node
+}
+
+/// Indented S-expression, one node per line.
+pub fn sexpr(node: &Node) -> String {
+    fn write_node(node: &Node, depth: usize, out: &mut String) {
+        let _ = write!(out, "{:width$}({}", "", node.label(), width = depth * 2);
+        for child in &node.children {
+            out.push('\n');
+            write_node(child, depth + 1, out);
+        }
+        out.push(')');
+    }
+    let mut out = String::new();
+    write_node(node, 0, &mut out);
+    out.push('\n');
+    out
+}
+
+pub fn json(node: &Node) -> Json {
+    let mut fields = vec![("kind".to_string(), Json::from(node.kind))];
+    if let Some(value) = &node.value {
+        fields.push(("value".to_string(), Json::from(value.as_str())));
+    }
+    if let Some(line) = node.line {
+        fields.push(("line".to_string(), Json::from(line)));
+    }
+    if let Some(captures) = &node.captures {
+        let captures = captures.iter().map(|c| Json::from(c.as_str())).collect();
+        fields.push(("captures".to_string(), Json::Array(captures)));
+    }
+    if !node.children.is_empty() {
+        let children = node.children.iter().map(json).collect();
+        fields.push(("children".to_string(), Json::Array(children)));
+    }
+    Json::Object(fields)
+}
+
+type WriteNode = fn(usize, &Node, &mut String);
+type WriteEdge = fn(usize, usize, &mut String);
+
+/// Writes every node and then its edges to the children, ids in preorder.
+fn walk(
+    node: &Node,
+    next_id: &mut usize,
+    out: &mut String,
+    write_node: WriteNode,
+    write_edge: WriteEdge,
+) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    write_node(id, node, out);
+    for child in &node.children {
+        let child_id = walk(child, next_id, out, write_node, write_edge);
+        write_edge(id, child_id, out);
+    }
+    id
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Graphviz digraph.
+pub fn dot(node: &Node) -> String {
+    let mut out = "digraph yappinglang {\n    node [shape=box];\n".to_string();
+    walk(
+        node,
+        &mut 0,
+        &mut out,
+        |id, node, out| {
+            let _ = writeln!(out, "    n{id} [label=\"{}\"];", escape(&node.label()));
+        },
+        |from, to, out| {
+            let _ = writeln!(out, "    n{from} -> n{to};");
+        },
+    );
+    out.push_str("}\n");
+    out
+}
+
+/// PlantUML use case diagram.
+pub fn puml(node: &Node) -> String {
+    let mut out = "@startuml\n".to_string();
+    walk(
+        node,
+        &mut 0,
+        &mut out,
+        |id, node, out| {
+            let _ = writeln!(out, "usecase \"{}\" as n{id}", escape(&node.label()));
+        },
+        |from, to, out| {
+            let _ = writeln!(out, "n{from} -d-> n{to}");
+        },
+    );
+    out.push_str("@enduml\n");
+    out
+}
@@ -1,16 +1,27 @@
 pub mod builtins;
 pub mod data;
-pub mod run_tree;
+pub mod debugger;
+pub mod error;
 mod external;
+pub mod format;
+pub mod image;
+pub mod modules;
+pub mod profiler;
+pub mod run_tree;
 
-use std::cell::RefCell;
 use crate::interpreter::data::Data;
+use crate::interpreter::debugger::Debugger;
+use crate::interpreter::error::Error;
+use crate::interpreter::modules::Modules;
+use crate::interpreter::profiler::Profiler;
 use crate::interpreter::run_tree::{Block, Exp, ExpData};
+use crate::regex::Regex;
+use std::cell::{RefCell, RefMut};
 use std::collections::{HashMap, HashSet};
+use std::io::{stdout, Write};
 use std::rc::Rc;
-use crate::interpreter::external::External;
 
-type StackFn = fn(&mut Vec<Data>, &HashMap<String, Data>, &mut BlockExec) -> Result<(), String>;
+type StackFn = fn(&mut Vec<Data>, &HashMap<String, Data>, &mut BlockExec) -> Result<(), Error>;
 
 pub struct Interpreter {
     pub root: BlockExec,
@@ -20,31 +31,202 @@ pub struct Interpreter {
 impl Interpreter {
     pub fn new(builtins: HashMap<String, Data>) -> Self {
         Self {
-            root: BlockExec::new(builtins),
+            root: BlockExec::new(builtins, Rc::new(Runtime::default())),
             stack: vec![],
         }
     }
-    pub fn load(&mut self, block: &crate::ast::Block) -> Rc<Block> {
-        run_tree::load(block)
+
+    /// Builtins and definitions made at the top level.
+    pub fn defs(&self) -> &HashMap<String, Data> {
+        &self.root.defs
+    }
+
+    pub fn set_defs(&mut self, defs: HashMap<String, Data>) {
+        self.root.defs = defs;
+    }
+
+    pub fn set_output(&mut self, out: Box<dyn Write>) {
+        *self.root.runtime.out.borrow_mut() = out;
+    }
+
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        *self.root.runtime.debugger.borrow_mut() = Some(debugger);
+    }
+
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        *self.root.runtime.profiler.borrow_mut() = Some(profiler);
     }
 
-    pub fn run(&mut self, block: Rc<Block>) -> Result<(), String> {
-        self.root.run_root(&mut self.stack, block)
+    pub fn take_tests(&mut self) -> Vec<(String, data::Block)> {
+        self.root.runtime.tests.take()
+    }
+
+    /// Runs `block` as a call from the root block.
+    pub fn run_block(&mut self, block: &data::Block) -> Result<(), Error> {
+        self.root
+            .new_and_run(&mut self.stack, &HashMap::new(), block)
+            .map_err(Error::outside_loop)
+    }
+
+    /// Directory searched by `Use-file` after the one of the including file.
+    pub fn add_library_path(&mut self, dir: std::path::PathBuf) {
+        self.root
+            .runtime
+            .modules
+            .borrow_mut()
+            .library_path
+            .push(dir);
+    }
+
+    /// Makes `Use-file "name` load `entry` and `Use-file "name/file.yp` load from `dir`.
+    pub fn add_package(
+        &mut self,
+        name: String,
+        dir: std::path::PathBuf,
+        entry: std::path::PathBuf,
+    ) {
+        self.root
+            .runtime
+            .modules
+            .borrow_mut()
+            .packages
+            .insert(name, (dir, entry));
     }
 
-    pub fn load_and_run(&mut self, block: &crate::ast::Block) -> Result<(), String> {
-        let block = self.load(block);
+    /// Writes top-level definitions and the stack to an image file.
+    pub fn save_image(&self, path: &std::path::Path) -> Result<(), String> {
+        let image = image::save(&self.root.defs, &self.stack)?;
+        std::fs::write(path, image).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Replaces top-level definitions and the stack with those of an image file.
+    pub fn load_image(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let (defs, stack) = image::load(&bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+        self.root.defs = defs;
+        self.stack = stack;
+        Ok(())
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.root.runtime.profiler.borrow_mut().take()
+    }
+    /// `file` is used to find files of `Use-file` and in error locations,
+    /// an existing path is made canonical.
+    pub fn load(&mut self, block: &crate::ast::Block, file: &str) -> Rc<Block> {
+        let file = std::fs::canonicalize(file)
+            .map(|path| path.to_string_lossy().into())
+            .unwrap_or_else(|_| file.into());
+        run_tree::load(block, &file)
+    }
+
+    pub fn run(&mut self, block: Rc<Block>) -> Result<(), Error> {
+        self.root
+            .run_root(&mut self.stack, block)
+            .map_err(Error::outside_loop)
+    }
+
+    #[allow(dead_code)]
+    pub fn load_and_run(&mut self, block: &crate::ast::Block, file: &str) -> Result<(), Error> {
+        let block = self.load(block, file);
         self.run(block)
     }
 }
 
-struct BlockExec {
+/// State shared by all `BlockExec` of one `Interpreter`.
+pub struct Runtime {
+    debugger: RefCell<Option<Debugger>>,
+    profiler: RefCell<Option<Profiler>>,
+    /// Cases registered by `Test`.
+    tests: RefCell<Vec<(String, data::Block)>>,
+    /// Where `Print`, `Debug` and `Timer` write.
+    out: RefCell<Box<dyn Write>>,
+    modules: RefCell<Modules>,
+    /// Compiled patterns of the `Regex-` builtins.
+    regexes: RefCell<HashMap<String, Rc<Regex>>>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self {
+            debugger: RefCell::new(None),
+            profiler: RefCell::new(None),
+            tests: RefCell::new(vec![]),
+            out: RefCell::new(Box::new(stdout())),
+            modules: RefCell::new(Modules::from_env()),
+            regexes: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl Runtime {
+    /// Compiles `pattern` once, the cache is dropped when it gets large.
+    pub fn regex(&self, pattern: &str) -> Result<Rc<Regex>, String> {
+        if let Some(regex) = self.regexes.borrow().get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Rc::new(Regex::new(pattern)?);
+        let mut regexes = self.regexes.borrow_mut();
+        if regexes.len() >= 256 {
+            regexes.clear();
+        }
+        regexes.insert(pattern.to_string(), regex.clone());
+        Ok(regex)
+    }
+
+    /// The debugger, `None` while it is paused and evaluates code on this runtime.
+    fn debugger(&self) -> Option<RefMut<'_, Debugger>> {
+        RefMut::filter_map(self.debugger.try_borrow_mut().ok()?, Option::as_mut).ok()
+    }
+
+    fn call(&self, name: &str, builtin: bool, stack_len: usize) {
+        if let Some(mut debugger) = self.debugger() {
+            debugger.call(name);
+        }
+        if let Some(profiler) = self.profiler.borrow_mut().as_mut() {
+            profiler.call(name, builtin, stack_len);
+        }
+    }
+
+    fn ret(&self) {
+        if let Some(mut debugger) = self.debugger() {
+            debugger.ret();
+        }
+        if let Some(profiler) = self.profiler.borrow_mut().as_mut() {
+            profiler.ret();
+        }
+    }
+
+    fn enter(&self, caller_defs: &HashMap<String, Data>, block: &Block) {
+        if let Some(mut debugger) = self.debugger() {
+            debugger.enter(caller_defs, block.file.clone(), block.capture_vars.clone());
+        }
+    }
+
+    fn leave(&self) {
+        if let Some(mut debugger) = self.debugger() {
+            debugger.leave();
+        }
+    }
+}
+
+pub struct BlockExec {
     defs: HashMap<String, Data>,
+    runtime: Rc<Runtime>,
+    /// File of the running block.
+    file: Rc<str>,
+    /// Names given to `Export`, all defs are exported when `None`.
+    exports: Option<HashSet<String>>,
 }
 
 impl BlockExec {
-    pub fn new(defs: HashMap<String, Data>) -> Self {
-        Self { defs }
+    pub fn new(defs: HashMap<String, Data>, runtime: Rc<Runtime>) -> Self {
+        Self {
+            defs,
+            runtime,
+            file: "".into(),
+            exports: None,
+        }
     }
 
     pub fn new_and_run(
@@ -52,28 +234,43 @@ impl BlockExec {
         stack: &mut Vec<Data>,
         prev_defs: &HashMap<String, Data>,
         block: &data::Block,
-    ) -> Result<(), String> {
-        let mut call = BlockExec::new(block.captured_vars.clone());
+    ) -> Result<(), Error> {
+        let mut call = BlockExec::new(block.captured_vars.clone(), self.runtime.clone());
         let mut defs = prev_defs.clone();
         for (k, v) in &self.defs {
             defs.insert(k.clone(), v.clone());
         }
-        call.run_block(stack, &defs, block.block.clone())
+        self.runtime.enter(&self.defs, &block.block);
+        let res = call.run_block(stack, &defs, block.block.clone());
+        self.runtime.leave();
+        res
     }
 
-    pub fn run_root(&mut self, stack: &mut Vec<Data>, block: Rc<Block>) -> Result<(), String> {
-        self.run_block(stack, &HashMap::new(), block)
+    pub fn run_root(&mut self, stack: &mut Vec<Data>, block: Rc<Block>) -> Result<(), Error> {
+        self.runtime.enter(&self.defs, &block);
+        let res = self.run_block(stack, &HashMap::new(), block);
+        self.runtime.leave();
+        res
     }
     pub fn run_block(
         &mut self,
         stack: &mut Vec<Data>,
         prev_defs: &HashMap<String, Data>,
         block: Rc<Block>,
-    ) -> Result<(), String> {
-        if let Some(exp) = &block.exp {
-            self.run_exp(stack, prev_defs, exp)?;
+    ) -> Result<(), Error> {
+        if block.module {
+            self.runtime.enter_module(&block.file)?;
         }
-        Ok(())
+        let file = std::mem::replace(&mut self.file, block.file.clone());
+        let res = match &block.exp {
+            Some(exp) => self.run_exp(stack, prev_defs, exp),
+            None => Ok(()),
+        };
+        self.file = file;
+        if block.module {
+            self.runtime.leave_module();
+        }
+        res
     }
 
     fn run_exp(
@@ -81,19 +278,40 @@ impl BlockExec {
         stack: &mut Vec<Data>,
         prev_defs: &HashMap<String, Data>,
         exp: &Exp,
-    ) -> Result<(), String> {
+    ) -> Result<(), Error> {
         if let Some(next_exp) = &exp.next_exp {
             self.run_exp(stack, prev_defs, next_exp)?;
         }
+        if let Some(mut debugger) = self.runtime.debugger() {
+            debugger
+                .exp(exp.line, &self.defs, stack, prev_defs, &self.runtime)
+                .map_err(Error::Abort)?;
+        }
+        self.eval_exp(stack, prev_defs, exp)
+            .map_err(|e| e.locate(&self.file, exp.line))
+    }
+
+    fn eval_exp(
+        &mut self,
+        stack: &mut Vec<Data>,
+        prev_defs: &HashMap<String, Data>,
+        exp: &Exp,
+    ) -> Result<(), Error> {
         match &exp.data {
             ExpData::Var(var) => {
                 if let Some(data) = self.get_data(var, prev_defs) {
                     match data {
                         Data::Fn(block) => {
-                            self.new_and_run(stack, prev_defs, &block)?;
+                            self.runtime.call(var, false, stack.len());
+                            let res = self.new_and_run(stack, prev_defs, &block);
+                            self.runtime.ret();
+                            res.map_err(Error::outside_loop)?;
                         }
                         Data::BuiltinFunc(func) => {
-                            func(stack, prev_defs, self)?;
+                            self.runtime.call(var, true, stack.len());
+                            let res = func(stack, prev_defs, self);
+                            self.runtime.ret();
+                            res?;
                         }
                         _ => stack.push(data),
                     }
@@ -109,6 +327,8 @@ impl BlockExec {
                 }));
             }
             ExpData::Integer(int) => stack.push(Data::Integer(*int)),
+            ExpData::BigInteger(int) => stack.push(Data::BigInteger(int.clone())),
+            ExpData::Rational(rat) => stack.push(Data::Rational(rat.clone())),
             ExpData::Decimal(dec) => stack.push(Data::Decimal(*dec)),
             ExpData::String(str) => stack.push(Data::String(str.clone())),
         }
@@ -119,11 +339,7 @@ impl BlockExec {
         if let Some(data) = self.defs.get(var) {
             Some(data.clone())
         } else {
-            if let Some(data) = defs_find.get(var) {
-                Some(data.clone())
-            } else {
-                None
-            }
+            defs_find.get(var).cloned()
         }
     }
 
@@ -131,7 +347,7 @@ impl BlockExec {
         &mut self,
         vars: &HashSet<String>,
         prev_defs: &HashMap<String, Data>,
-    ) -> Result<HashMap<String, Data>, String> {
+    ) -> Result<HashMap<String, Data>, Error> {
         let mut captured_vars = HashMap::new();
         for var in vars {
             if let Some(data) = self.get_data(var, prev_defs) {
@@ -143,7 +359,7 @@ impl BlockExec {
         Ok(captured_vars)
     }
 
-    fn error<T>(&self, message: String) -> Result<T, String> {
-        Err(format!("error: {}", message))
+    fn error<T>(&self, message: String) -> Result<T, Error> {
+        Err(Error::new("undefined", message))
     }
 }
@@ -1,23 +1,29 @@
-use std::cell::RefCell;
-use crate::interpreter::data::Data;
+use crate::bigint::BigInt;
+use crate::interpreter::data::{self, Data, ErrorValue};
+use crate::interpreter::error::Error;
+use crate::interpreter::format::Template;
+use crate::interpreter::modules;
 use crate::interpreter::BlockExec;
+use crate::rational::Rational;
+use crate::regex::{Captures, Regex};
 use crate::utils::{print_stack, ResultToString};
-use std::collections::{HashMap, VecDeque};
-use std::io::Read;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
 use std::mem;
 use std::ops::Add;
-use crate::interpreter::external::External;
+use std::rc::Rc;
+/// Returns a raised error of `$kind`, `type` by default. `$func` only names the failing
+/// builtin in the source, the message scripts see through `Error-message` is `$msg` alone.
 macro_rules! error {
+    ($func:ident, $kind:literal, $msg:expr) => {
+        return Err(Error::new($kind, $msg.to_string()))
+    };
     ($func:ident, $msg:expr) => {
-        return Err(format!(
-            "builtin error func '{}' line {}: {}",
-            stringify!($func),
-            line!(),
-            $msg
-        ));
+        error!($func, "type", $msg)
     };
     ($msg:expr) => {
-        return Err(format!("builtin error line {}: {}", line!(), $msg));
+        return Err(Error::new("stack", $msg.to_string()))
     };
 }
 
@@ -26,7 +32,7 @@ macro_rules! s {
         if let Some(v) = $opt {
             v
         } else {
-            return error!("empty stack when pop");
+            error!("empty stack when pop");
         }
     };
 }
@@ -55,30 +61,54 @@ macro_rules! binary_op_int {
     };
 }
 
+/// `$checked` is the method of `i64`, `BigInt` and `Rational` returning `None` on overflow
+/// or division by zero. Integers that overflow `i64` are retried as `BigInt`.
 macro_rules! binary_op_num {
-    ($defs:ident, $prefix:ident, $op:tt) => {
-        func!($defs, $prefix, stringify!($op).to_string(), |a,b,c| number_binary_op(|i1,i2| i1 $op i2,|d1,d2| d1 $op d2,a,b,c));
+    ($defs:ident, $prefix:ident, $op:tt, $checked:ident) => {
+        func!($defs, $prefix, stringify!($op).to_string(), |a,b,c| number_binary_op(stringify!($op),BinaryOps {
+            int: |i1,i2| i1.$checked(i2),
+            big: |b1,b2| b1.$checked(b2),
+            rational: |r1,r2| r1.$checked(r2),
+            decimal: |d1,d2| d1 $op d2,
+        },a,b,c));
     };
-    ($defs:ident, $prefix:ident, $op:tt, $name:literal) => {
-        func!($defs, $prefix, $name.to_string(), |a,b,c| number_binary_op(|i1,i2| i1 $op i2,|d1,d2| d1 $op d2,a,b,c));
+}
+
+/// `i64` only, `$op` returns `None` on division by zero.
+macro_rules! binary_op_int_checked {
+    ($defs:ident, $prefix:ident, $name:literal, $op:expr) => {
+        func!($defs, $prefix, $name, |a, b, c| integer_checked_op(
+            $name, $op, a, b, c
+        ));
     };
 }
 
-macro_rules! binary_op_bool {
-    ($defs:ident, $prefix:ident, $op:tt) => {
-        func!($defs, $prefix, stringify!($op).to_string(), |a,b,c| integer_binary_op(|i1,i2| if i1 $op i2 {1} else {0},a,b,c));
+/// `$ordering` is the `Ordering` method deciding the result, e.g. `is_lt`.
+macro_rules! binary_op_cmp {
+    ($defs:ident, $prefix:ident, $op:tt, $ordering:ident) => {
+        func!($defs, $prefix, stringify!($op).to_string(), |a, b, c| {
+            compare_op(stringify!($op), Ordering::$ordering, a, b, c)
+        });
     };
-    ($defs:ident, $prefix:ident, $op:tt, $name:literal) => {
-        func!($defs, $prefix, $name.to_string(), |a,b,c| integer_binary_op(|i1,i2| if i1 $op i2 {1} else {0},a,b,c));
-    }
 }
 
 macro_rules! unary_op_num {
-    ($defs:ident, $prefix:ident, $op:tt) => {
-        func!($defs, $prefix, stringify!($op).to_string(), |a,b,c| number_unary_op(|i| $op i,|d| $op d,a,b,c));
+    ($defs:ident, $prefix:ident, $op:tt, $name:literal, $checked:ident) => {
+        func!($defs, $prefix, $name.to_string(), |a,b,c| number_unary_op($name,UnaryOps {
+            int: |i| i.$checked(),
+            big: |b| b.$checked(),
+            rational: |r| r.$checked(),
+            decimal: |d| $op d,
+        },a,b,c));
     };
-    ($defs:ident, $prefix:ident, $op:tt, $name:literal) => {
-        func!($defs, $prefix, $name.to_string(), |a,b,c| number_unary_op(|i| $op i,|d| $op d,a,b,c));
+}
+
+/// Any number as `f64`, the result is always a Decimal.
+macro_rules! unary_op_dec {
+    ($defs:ident, $prefix:ident, $name:literal, $op:expr) => {
+        func!($defs, $prefix, $name, |a, b, c| decimal_unary_op(
+            $name, $op, a, b, c
+        ));
     };
 }
 
@@ -88,10 +118,9 @@ macro_rules! type_assert {
             $defs,
             $prefix,
             format!("{}!", stringify!($typ)),
-            |a, b, c| {
+            |a, _, _| {
                 let d = s!(a.last());
-                if let Data::$typ(_) = d {
-                } else {
+                if d.type_name() != stringify!($typ) {
                     error!(
                         type_assert,
                         format!("expect {}, found {}", stringify!($typ), d)
@@ -108,26 +137,37 @@ macro_rules! type_check {
         func!(
             $defs,
             $prefix,
-            format!("{}!", stringify!($typ)),
-            |a, b, c| {
+            format!("{}?", stringify!($typ)),
+            |a, _, _| {
                 let d = s!(a.pop());
-                if let Data::$typ(_) = d {
-                    a.push(Data::Integer(1));
-                } else {
-                    a.push(Data::Integer(0));
-                }
+                a.push(Data::Integer((d.type_name() == stringify!($typ)) as i64));
                 Ok(())
             }
         );
     };
 }
 
+/// Builtins `Import` can load by name.
+pub fn group(name: &str) -> Option<HashMap<String, Data>> {
+    match name {
+        "base" => Some(base(HashMap::new(), "")),
+        "math" => Some(math(math_and_logic(HashMap::new(), ""), "")),
+        _ => None,
+    }
+}
+
 pub fn base(mut defs: HashMap<String, Data>, prefix: &str) -> HashMap<String, Data> {
     func!(defs, prefix, "Def-fn", def_fn);
     func!(defs, prefix, "Def", def);
     func!(defs, prefix, "If", _if);
     func!(defs, prefix, "Do", _do);
     func!(defs, prefix, "Do-local", _do_local);
+    func!(defs, prefix, "While", _while);
+    func!(defs, prefix, "Repeat", repeat);
+    func!(defs, prefix, "Times", times);
+    func!(defs, prefix, "For", _for);
+    func!(defs, prefix, "Break", _break);
+    func!(defs, prefix, "Continue", _continue);
     func!(defs, prefix, "Get-fn", get_fn);
     func!(defs, prefix, "Get-def", get_def);
     func!(defs, prefix, "Has-def", has_def);
@@ -135,8 +175,20 @@ pub fn base(mut defs: HashMap<String, Data>, prefix: &str) -> HashMap<String, Da
     func!(defs, prefix, "Debug", debug);
     func!(defs, prefix, "Timer", timer);
     func!(defs, prefix, "Assert", assert);
+    func!(defs, prefix, "Assert-eq", assert_eq);
+    func!(defs, prefix, "Assert-error", assert_error);
+    func!(defs, prefix, "Test", test);
+    func!(defs, prefix, "Throw", throw);
+    func!(defs, prefix, "Try", _try);
+    func!(defs, prefix, "Catch", catch);
+    func!(defs, prefix, "New-error", new_error);
+    func!(defs, prefix, "Error-kind", error_kind);
+    func!(defs, prefix, "Error-message", error_message);
     func!(defs, prefix, "Use-file", use_file);
     func!(defs, prefix, "Try-use-file", try_use_file);
+    func!(defs, prefix, "Import", import);
+    func!(defs, prefix, "Import-only", import_only);
+    func!(defs, prefix, "Export", export);
     func!(defs, prefix, "Any!", any_assert);
     func!(defs, prefix, "Any?", any_check);
     func!(defs, prefix, "Drop", drop);
@@ -146,71 +198,290 @@ pub fn base(mut defs: HashMap<String, Data>, prefix: &str) -> HashMap<String, Da
     type_assert!(defs, prefix, String);
     type_assert!(defs, prefix, Integer);
     type_assert!(defs, prefix, Decimal);
+    type_assert!(defs, prefix, Rational);
     type_assert!(defs, prefix, List);
     type_assert!(defs, prefix, Dict);
     type_assert!(defs, prefix, Box);
     type_assert!(defs, prefix, Block);
     type_assert!(defs, prefix, Fn);
     type_assert!(defs, prefix, External);
+    type_assert!(defs, prefix, Error);
 
     type_check!(defs, prefix, String);
     type_check!(defs, prefix, Integer);
     type_check!(defs, prefix, Decimal);
+    type_check!(defs, prefix, Rational);
     type_check!(defs, prefix, List);
     type_check!(defs, prefix, Dict);
     type_check!(defs, prefix, Box);
     type_check!(defs, prefix, Block);
     type_check!(defs, prefix, Fn);
     type_check!(defs, prefix, External);
+    type_check!(defs, prefix, Error);
 
     func!(defs, prefix, "To-integer", to_integer);
     func!(defs, prefix, "To-decimal", to_decimal);
+    func!(defs, prefix, "To-rational", to_rational);
+    func!(defs, prefix, "To-fixed", to_fixed);
+    func!(defs, prefix, "Numerator", numerator);
+    func!(defs, prefix, "Denominator", denominator);
     func!(defs, prefix, "To-string", to_string);
     func!(defs, prefix, "To-list", to_list);
 
     func!(defs, prefix, "String-join", string_join);
     func!(defs, prefix, "String-chars", string_chars);
+    func!(defs, prefix, "String-len", string_len);
+    func!(defs, prefix, "String-split", string_split);
+    func!(defs, prefix, "String-slice", string_slice);
+    func!(defs, prefix, "String-find", string_find);
+    func!(defs, prefix, "String-replace", string_replace);
+    func!(defs, prefix, "String-upper", |a, _, _| string_map(
+        "String-upper",
+        |s| s.to_uppercase(),
+        a
+    ));
+    func!(defs, prefix, "String-lower", |a, _, _| string_map(
+        "String-lower",
+        |s| s.to_lowercase(),
+        a
+    ));
+    func!(defs, prefix, "String-trim", |a, _, _| string_map(
+        "String-trim",
+        |s| s.trim().to_string(),
+        a
+    ));
+    func!(defs, prefix, "String-starts?", |a, _, _| string_test(
+        "String-starts?",
+        |s, p| s.starts_with(p),
+        a
+    ));
+    func!(defs, prefix, "String-ends?", |a, _, _| string_test(
+        "String-ends?",
+        |s, p| s.ends_with(p),
+        a
+    ));
+    func!(defs, prefix, "String-repeat", string_repeat);
+    func!(defs, prefix, "Char-code", char_code);
+    func!(defs, prefix, "Code-char", code_char);
+    func!(defs, prefix, "Format", format);
+    func!(defs, prefix, "Regex-match?", regex_match);
+    func!(defs, prefix, "Regex-find", regex_find);
+    func!(defs, prefix, "Regex-find-all", regex_find_all);
+    func!(defs, prefix, "Regex-replace", regex_replace);
 
     func!(defs, prefix, "List", list);
     func!(defs, prefix, "List-reverse", list_reverse);
     func!(defs, prefix, "List-stack", list_stack);
+    func!(defs, prefix, "Map", map);
+    func!(defs, prefix, "Filter", filter);
+    func!(defs, prefix, "Fold", fold);
+    func!(defs, prefix, "Reduce", reduce);
+    func!(defs, prefix, "Each", each);
+    func!(defs, prefix, "Any-of?", |a, b, c| any_all(
+        "Any-of?", true, a, b, c
+    ));
+    func!(defs, prefix, "All-of?", |a, b, c| any_all(
+        "All-of?", false, a, b, c
+    ));
+    func!(defs, prefix, "Zip", zip);
+    func!(defs, prefix, "Flat-map", flat_map);
+    func!(defs, prefix, "Range", range);
+    func!(defs, prefix, "List-get", list_get);
+    func!(defs, prefix, "List-set", list_set);
+    func!(defs, prefix, "List-slice", list_slice);
+    func!(defs, prefix, "List-concat", list_concat);
+    func!(defs, prefix, "List-index-of", list_index_of);
+    func!(defs, prefix, "List-contains?", list_contains);
+    func!(defs, prefix, "Sort", sort);
+    func!(defs, prefix, "Sort-by", sort_by);
+    func!(defs, prefix, "Sort-with", sort_with);
+    func!(defs, prefix, "Unique", unique);
+    func!(defs, prefix, "Group-by", group_by);
 
     defs
 }
 pub fn math_and_logic(mut defs: HashMap<String, Data>, prefix: &str) -> HashMap<String, Data> {
-    binary_op_num!(defs, prefix, +);
-    binary_op_num!(defs, prefix, -);
-    binary_op_num!(defs, prefix, *);
-    binary_op_num!(defs, prefix, /);
-    binary_op_num!(defs, prefix, %);
+    binary_op_num!(defs, prefix, +, checked_add);
+    binary_op_num!(defs, prefix, -, checked_sub);
+    binary_op_num!(defs, prefix, *, checked_mul);
+    binary_op_num!(defs, prefix, /, checked_div);
+    binary_op_num!(defs, prefix, %, checked_rem);
+    binary_op_int_checked!(defs, prefix, "Wrapping+", |i1, i2| Some(
+        i1.wrapping_add(i2)
+    ));
+    binary_op_int_checked!(defs, prefix, "Wrapping-", |i1, i2| Some(
+        i1.wrapping_sub(i2)
+    ));
+    binary_op_int_checked!(defs, prefix, "Wrapping*", |i1, i2| Some(
+        i1.wrapping_mul(i2)
+    ));
+    binary_op_int_checked!(defs, prefix, "Wrapping/", |i1, i2| (i2 != 0)
+        .then(|| i1.wrapping_div(i2)));
+    binary_op_int_checked!(defs, prefix, "Saturating+", |i1, i2| Some(
+        i1.saturating_add(i2)
+    ));
+    binary_op_int_checked!(defs, prefix, "Saturating-", |i1, i2| Some(
+        i1.saturating_sub(i2)
+    ));
+    binary_op_int_checked!(defs, prefix, "Saturating*", |i1, i2| Some(
+        i1.saturating_mul(i2)
+    ));
+    binary_op_int_checked!(defs, prefix, "Saturating/", |i1, i2| (i2 != 0)
+        .then(|| i1.saturating_div(i2)));
     binary_op_int!(defs, prefix, &, "And");
     binary_op_int!(defs, prefix, ^, "Xor");
     binary_op_int!(defs, prefix, |, "Or");
-    binary_op_bool!(defs, prefix, ==);
-    binary_op_bool!(defs, prefix, !=);
-    binary_op_bool!(defs, prefix, <);
-    binary_op_bool!(defs, prefix, <=);
-    binary_op_bool!(defs, prefix, >);
-    binary_op_bool!(defs, prefix, >=);
-    binary_op_bool!(defs, prefix, >=);
-    unary_op_num!(defs, prefix, -, "Neg");
-    func!(defs, prefix, "To-bool", |a,b,c| integer_unary_op(|n| if n==0 {0} else {1},a,b,c));
-    func!(defs, prefix, "Not", |a,b,c| integer_unary_op(|n| if n==0 {1} else {0},a,b,c));
+    func!(defs, prefix, "==", |a, _, _| {
+        let eq = equal_op(a)?;
+        a.push(Data::Integer(eq as i64));
+        Ok(())
+    });
+    func!(defs, prefix, "!=", |a, _, _| {
+        let eq = equal_op(a)?;
+        a.push(Data::Integer(!eq as i64));
+        Ok(())
+    });
+    binary_op_cmp!(defs, prefix, <, is_lt);
+    binary_op_cmp!(defs, prefix, <=, is_le);
+    binary_op_cmp!(defs, prefix, >, is_gt);
+    binary_op_cmp!(defs, prefix, >=, is_ge);
+    func!(defs, prefix, "Compare", compare);
+    unary_op_num!(defs, prefix, -, "Neg", checked_neg);
+    func!(defs, prefix, "To-bool", |a, b, c| integer_unary_op(
+        |n| if n == 0 { 0 } else { 1 },
+        a,
+        b,
+        c
+    ));
+    func!(defs, prefix, "Not", |a, b, c| integer_unary_op(
+        |n| if n == 0 { 1 } else { 0 },
+        a,
+        b,
+        c
+    ));
     defs.insert(format!("{}True", prefix), Data::Integer(1));
     defs.insert(format!("{}False", prefix), Data::Integer(0));
     defs
 }
 
-//fn def_fn(stack: &mut Vec<Data>, blocks: &mut Vec<Rc<Block>>, prev_defs: &HashMap<String,Data>, block_exec: &mut BlockExec) -> Result<(), String> {}
+pub fn math(mut defs: HashMap<String, Data>, prefix: &str) -> HashMap<String, Data> {
+    func!(defs, prefix, "Abs", |a, b, c| number_unary_op(
+        "Abs",
+        UnaryOps {
+            int: |i| i.checked_abs(),
+            big: |b| Some(b.abs()),
+            rational: |r| Some(r.abs()),
+            decimal: f64::abs,
+        },
+        a,
+        b,
+        c
+    ));
+    func!(defs, prefix, "Floor", |a, b, c| number_unary_op(
+        "Floor",
+        UnaryOps {
+            int: Some,
+            big: |b| Some(b.clone()),
+            rational: |r| Some(Rational::from(r.floor())),
+            decimal: f64::floor,
+        },
+        a,
+        b,
+        c
+    ));
+    func!(defs, prefix, "Ceil", |a, b, c| number_unary_op(
+        "Ceil",
+        UnaryOps {
+            int: Some,
+            big: |b| Some(b.clone()),
+            rational: |r| Some(Rational::from(r.ceil())),
+            decimal: f64::ceil,
+        },
+        a,
+        b,
+        c
+    ));
+    func!(defs, prefix, "Round", |a, b, c| number_unary_op(
+        "Round",
+        UnaryOps {
+            int: Some,
+            big: |b| Some(b.clone()),
+            rational: |r| Some(Rational::from(r.round())),
+            decimal: f64::round,
+        },
+        a,
+        b,
+        c
+    ));
+    func!(defs, prefix, "Div", |a, b, c| number_binary_op(
+        "Div",
+        BinaryOps {
+            int: div_floor,
+            big: |b1, b2| Some(b1.div_mod_floor(b2)?.0),
+            rational: |r1, r2| Some(Rational::from(r1.checked_div(r2)?.floor())),
+            decimal: |d1, d2| (d1 / d2).floor(),
+        },
+        a,
+        b,
+        c
+    ));
+    func!(defs, prefix, "Mod", |a, b, c| number_binary_op(
+        "Mod",
+        BinaryOps {
+            int: mod_floor,
+            big: |b1, b2| Some(b1.div_mod_floor(b2)?.1),
+            rational: |r1, r2| {
+                let quotient = Rational::from(r1.checked_div(r2)?.floor());
+                r1.checked_sub(&r2.checked_mul(&quotient)?)
+            },
+            decimal: |d1, d2| d1 - d2 * (d1 / d2).floor(),
+        },
+        a,
+        b,
+        c
+    ));
+    func!(defs, prefix, "Min", |a, _, _| min_max(
+        "Min",
+        Ordering::is_le,
+        a
+    ));
+    func!(defs, prefix, "Max", |a, _, _| min_max(
+        "Max",
+        Ordering::is_ge,
+        a
+    ));
+    func!(defs, prefix, "Pow", pow);
+    func!(defs, prefix, "Gcd", gcd);
+    func!(defs, prefix, "Shl", |a, _, _| shift("Shl", true, a));
+    func!(defs, prefix, "Shr", |a, _, _| shift("Shr", false, a));
+    func!(defs, prefix, "Popcount", popcount);
+    unary_op_dec!(defs, prefix, "Sqrt", f64::sqrt);
+    unary_op_dec!(defs, prefix, "Exp", f64::exp);
+    unary_op_dec!(defs, prefix, "Log", f64::ln);
+    unary_op_dec!(defs, prefix, "Log2", f64::log2);
+    unary_op_dec!(defs, prefix, "Log10", f64::log10);
+    unary_op_dec!(defs, prefix, "Sin", f64::sin);
+    unary_op_dec!(defs, prefix, "Cos", f64::cos);
+    unary_op_dec!(defs, prefix, "Tan", f64::tan);
+    unary_op_dec!(defs, prefix, "Asin", f64::asin);
+    unary_op_dec!(defs, prefix, "Acos", f64::acos);
+    unary_op_dec!(defs, prefix, "Atan", f64::atan);
+    func!(defs, prefix, "Atan2", atan2);
+    defs.insert(format!("{}Pi", prefix), Data::Decimal(std::f64::consts::PI));
+    defs.insert(format!("{}E", prefix), Data::Decimal(std::f64::consts::E));
+    defs
+}
+
+//fn def_fn(stack: &mut Vec<Data>, blocks: &mut Vec<Rc<Block>>, prev_defs: &HashMap<String,Data>, block_exec: &mut BlockExec) -> Result<(), Error> {}
 fn def_fn(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
+    _prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let name = s!(stack.pop());
-    if let Data::String(name) = (name) {
+    if let Data::String(name) = name {
         let block = s!(stack.pop());
-        if let Data::Block(block) = (block) {
+        if let Data::Block(block) = block {
             block_exec.defs.insert(name, Data::Fn(block));
         } else {
             error!(def_fn, format!("expect Block, found {}", block));
@@ -222,11 +493,11 @@ fn def_fn(
 }
 fn def(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
+    _prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let name = s!(stack.pop());
-    if let Data::String(name) = (name) {
+    if let Data::String(name) = name {
         let data = s!(stack.pop());
         block_exec.defs.insert(name, data);
     } else {
@@ -239,7 +510,7 @@ fn _if(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let bool = s!(stack.pop());
     let true_data = s!(stack.pop());
     let false_data = s!(stack.pop());
@@ -254,7 +525,7 @@ fn _if(
         Data::Block(block) => {
             block_exec.new_and_run(stack, prev_defs, &block)?;
             let bool = s!(stack.pop());
-            if let Data::Integer(bool) = (bool) {
+            if let Data::Integer(bool) = bool {
                 if bool != 0 {
                     stack.push(true_data);
                 } else {
@@ -271,36 +542,170 @@ fn _if(
     Ok(())
 }
 
+/// One function per number representation, tried from `int` to `decimal`.
+struct NumberOps<I, B, R, D> {
+    int: I,
+    big: B,
+    rational: R,
+    decimal: D,
+}
+
+type BinaryOps = NumberOps<
+    fn(i64, i64) -> Option<i64>,
+    fn(&BigInt, &BigInt) -> Option<BigInt>,
+    fn(&Rational, &Rational) -> Option<Rational>,
+    fn(f64, f64) -> f64,
+>;
+
+type UnaryOps = NumberOps<
+    fn(i64) -> Option<i64>,
+    fn(&BigInt) -> Option<BigInt>,
+    fn(&Rational) -> Option<Rational>,
+    fn(f64) -> f64,
+>;
+
+/// Integer with Integer gives an Integer, with Rational a Rational, anything with Decimal a Decimal.
 fn number_binary_op(
-    int_op: fn(i64, i64) -> i64,
-    dec_op: fn(f64, f64) -> f64,
+    op: &str,
+    ops: BinaryOps,
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let num1 = s!(stack.pop());
+    let num2 = s!(stack.pop());
+    if let (Data::Integer(i1), Data::Integer(i2)) = (&num1, &num2) {
+        if let Some(int) = (ops.int)(*i1, *i2) {
+            stack.push(Data::Integer(int));
+            return Ok(());
+        }
+    }
+    if let (Some(b1), Some(b2)) = (num1.as_big_int(), num2.as_big_int()) {
+        match (ops.big)(&b1, &b2) {
+            Some(int) => stack.push(Data::integer(int)),
+            None => {
+                error!(
+                    number_binary_op,
+                    "arithmetic",
+                    format!("division by zero in {op} {num1} {num2}")
+                );
+            }
+        }
+        return Ok(());
+    }
+    if let (Some(r1), Some(r2)) = (num1.as_rational(), num2.as_rational()) {
+        match (ops.rational)(&r1, &r2) {
+            Some(rat) => stack.push(Data::Rational(Rc::new(rat))),
+            None => {
+                error!(
+                    number_binary_op,
+                    "arithmetic",
+                    format!("division by zero in {op} {num1} {num2}")
+                );
+            }
+        }
+        return Ok(());
+    }
+    match (num1.as_f64(), num2.as_f64()) {
+        (Some(d1), Some(d2)) => stack.push(Data::Decimal((ops.decimal)(d1, d2))),
+        _ => {
+            error!(
+                number_binary_op,
+                format!("expected 2 numbers, found {} {}", num1, num2)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn integer_checked_op(
+    op: &str,
+    int_op: fn(i64, i64) -> Option<i64>,
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let num1 = s!(stack.pop());
     let num2 = s!(stack.pop());
     match (num1, num2) {
-        (Data::Integer(i1), Data::Integer(i2)) => stack.push(Data::Integer(int_op(i1, i2))),
-        (Data::Decimal(d1), Data::Integer(i2)) => stack.push(Data::Decimal(dec_op(d1, i2 as f64))),
-        (Data::Integer(i1), Data::Decimal(d2)) => stack.push(Data::Decimal(dec_op(i1 as f64, d2))),
-        (Data::Decimal(d1), Data::Decimal(d2)) => stack.push(Data::Decimal(dec_op(d1, d2))),
+        (Data::Integer(i1), Data::Integer(i2)) => match int_op(i1, i2) {
+            Some(int) => stack.push(Data::Integer(int)),
+            None => {
+                error!(
+                    integer_checked_op,
+                    "arithmetic",
+                    format!("division by zero in {op} {i1} {i2}")
+                );
+            }
+        },
         (n1, n2) => {
             error!(
-                number_binary_op,
-                format!("expected 2 Integer or Decimal, found {} {}", n1, n2)
+                integer_checked_op,
+                format!(
+                    "expected 2 Integer in the 64-bit range for {op}, found {} {}",
+                    n1, n2
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+fn equal_op(stack: &mut Vec<Data>) -> Result<bool, Error> {
+    let data1 = s!(stack.pop());
+    let data2 = s!(stack.pop());
+    Ok(data1.equal(&data2))
+}
+
+fn ordering(op: &str, stack: &mut Vec<Data>) -> Result<Ordering, Error> {
+    let data1 = s!(stack.pop());
+    let data2 = s!(stack.pop());
+    match data1.compare(&data2) {
+        Some(ordering) => Ok(ordering),
+        None => {
+            error!(
+                compare,
+                format!(
+                    "can not order {} {} and {} {} with {op}",
+                    data1.type_name(),
+                    data1,
+                    data2.type_name(),
+                    data2
+                )
             );
         }
     }
+}
+
+fn compare_op(
+    op: &str,
+    test: fn(Ordering) -> bool,
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let ordering = ordering(op, stack)?;
+    stack.push(Data::Integer(test(ordering) as i64));
+    Ok(())
+}
+
+/// -1, 0 or 1 when the first is smaller, equal or larger.
+fn compare(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let ordering = ordering("Compare", stack)?;
+    stack.push(Data::Integer(ordering as i64));
     Ok(())
 }
 
 fn integer_binary_op(
     int_op: fn(i64, i64) -> i64,
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let num1 = s!(stack.pop());
     let num2 = s!(stack.pop());
     match (num1, num2) {
@@ -316,31 +721,269 @@ fn integer_binary_op(
 }
 
 fn number_unary_op(
-    int_op: fn(i64) -> i64,
-    dec_op: fn(f64) -> f64,
+    op: &str,
+    ops: UnaryOps,
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let num1 = s!(stack.pop());
+    if let Data::Integer(i1) = num1 {
+        if let Some(int) = (ops.int)(i1) {
+            stack.push(Data::Integer(int));
+            return Ok(());
+        }
+    }
     match num1 {
-        Data::Integer(i1) => stack.push(Data::Integer(int_op(i1))),
-        Data::Decimal(d1) => stack.push(Data::Decimal(dec_op(d1))),
+        Data::Integer(_) | Data::BigInteger(_) => {
+            match num1.as_big_int().and_then(|b| (ops.big)(&b)) {
+                Some(int) => stack.push(Data::integer(int)),
+                None => {
+                    error!(
+                        number_unary_op,
+                        "arithmetic",
+                        format!("{op} failed for {num1}")
+                    );
+                }
+            }
+        }
+        Data::Rational(r1) => match (ops.rational)(&r1) {
+            Some(rat) => stack.push(Data::Rational(Rc::new(rat))),
+            None => {
+                error!(
+                    number_unary_op,
+                    "arithmetic",
+                    format!("{op} failed for {r1}")
+                );
+            }
+        },
+        Data::Decimal(d1) => stack.push(Data::Decimal((ops.decimal)(d1))),
         n1 => {
+            error!(number_unary_op, format!("expected a number, found {}", n1));
+        }
+    }
+    Ok(())
+}
+fn decimal_unary_op(
+    op: &str,
+    dec_op: fn(f64) -> f64,
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let num1 = s!(stack.pop());
+    match num1.as_f64() {
+        Some(d1) => stack.push(Data::Decimal(dec_op(d1))),
+        None => {
             error!(
-                number_unary_op,
-                format!("expected Integer or Decimal, found {}", n1)
+                decimal_unary_op,
+                format!("expected a number for {op}, found {}", num1)
             );
         }
     }
     Ok(())
 }
+
+/// `Atan2 y x`, the angle of the point (x, y).
+fn atan2(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let y = s!(stack.pop());
+    let x = s!(stack.pop());
+    match (y.as_f64(), x.as_f64()) {
+        (Some(y), Some(x)) => stack.push(Data::Decimal(y.atan2(x))),
+        _ => {
+            error!(atan2, format!("expected 2 numbers, found {} {}", y, x));
+        }
+    }
+    Ok(())
+}
+
+/// Quotient rounded down, `None` also on overflow so `BigInt` takes over.
+fn div_floor(i1: i64, i2: i64) -> Option<i64> {
+    let quotient = i1.checked_div(i2)?;
+    let remainder = i1.checked_rem(i2)?;
+    if remainder != 0 && (remainder < 0) != (i2 < 0) {
+        return quotient.checked_sub(1);
+    }
+    Some(quotient)
+}
+
+/// Remainder with the sign of the divisor.
+fn mod_floor(i1: i64, i2: i64) -> Option<i64> {
+    let remainder = i1.checked_rem(i2)?;
+    if remainder != 0 && (remainder < 0) != (i2 < 0) {
+        return remainder.checked_add(i2);
+    }
+    Some(remainder)
+}
+
+/// The first value when `keep_first` holds for its ordering to the second, else the second.
+fn min_max(op: &str, keep_first: fn(Ordering) -> bool, stack: &mut Vec<Data>) -> Result<(), Error> {
+    let kept = match stack.len().checked_sub(2) {
+        Some(second) => stack[second..].to_vec(),
+        None => {
+            error!("empty stack when pop");
+        }
+    };
+    let ordering = ordering(op, stack)?;
+    stack.push(kept[keep_first(ordering) as usize].clone());
+    Ok(())
+}
+
+/// Largest result of an exact `Pow` in bits.
+const MAX_POW_BITS: u64 = 1 << 20;
+
+/// Base 2 logarithm of the magnitude, also of ones too large for `f64`.
+fn log2(int: &BigInt) -> f64 {
+    let shift = int.bits().saturating_sub(64) as u32;
+    int.abs().shr(shift).to_f64().log2() + shift as f64
+}
+
+/// `Pow base exponent`. Integer and Rational powers are exact, a negative Integer exponent
+/// gives a Rational, anything else is a Decimal. Exact results over `MAX_POW_BITS` fail.
+fn pow(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let base = s!(stack.pop());
+    let exponent = s!(stack.pop());
+    let exact = match &base {
+        Data::Integer(_) | Data::BigInteger(_) | Data::Rational(_) => exponent.as_big_int(),
+        _ => None,
+    };
+    match (exact, base.as_rational()) {
+        (Some(exp), Some(rat)) => {
+            let bits = rat.numerator().bits().max(rat.denominator().bits());
+            let exp = if bits <= 1 {
+                // 0, 1 and -1 only depend on the sign and parity of the exponent.
+                let parity = exp
+                    .checked_rem(&BigInt::from(2))
+                    .and_then(|r| r.to_i64())
+                    .unwrap_or_default()
+                    .abs();
+                match exp.is_negative() {
+                    _ if exp.is_zero() => 0,
+                    true => -2 - parity,
+                    false => 2 + parity,
+                }
+            } else {
+                // The result has floor(exp * log2 |base|) + 1 bits.
+                let log = log2(rat.numerator()).max(log2(rat.denominator()));
+                match exp.to_i64() {
+                    Some(exp) if (exp.unsigned_abs() as f64) * log < MAX_POW_BITS as f64 => exp,
+                    _ => {
+                        error!(
+                            pow,
+                            "arithmetic",
+                            format!("result of Pow {base} {exponent} is over {MAX_POW_BITS} bits")
+                        );
+                    }
+                }
+            };
+            match (&base, rat.pow(exp)) {
+                (Data::Integer(_) | Data::BigInteger(_), Some(power)) if exp >= 0 => {
+                    stack.push(Data::integer(power.numerator().clone()))
+                }
+                (_, Some(power)) => stack.push(Data::Rational(Rc::new(power))),
+                (_, None) => {
+                    error!(
+                        pow,
+                        "arithmetic",
+                        format!("division by zero in Pow {base} {exponent}")
+                    );
+                }
+            }
+        }
+        _ => match (base.as_f64(), exponent.as_f64()) {
+            (Some(d1), Some(d2)) => stack.push(Data::Decimal(d1.powf(d2))),
+            _ => {
+                error!(
+                    pow,
+                    format!("expected 2 numbers, found {} {}", base, exponent)
+                );
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Greatest common divisor of 2 Integer, never negative.
+fn gcd(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let num1 = s!(stack.pop());
+    let num2 = s!(stack.pop());
+    match (num1.as_big_int(), num2.as_big_int()) {
+        (Some(b1), Some(b2)) => stack.push(Data::integer(b1.gcd(&b2))),
+        _ => {
+            error!(gcd, format!("expected 2 Integer, found {} {}", num1, num2));
+        }
+    }
+    Ok(())
+}
+
+/// `Shl i n` is `i * 2^n`, `Shr i n` is `i / 2^n` rounded down, both for any size.
+fn shift(op: &str, left: bool, stack: &mut Vec<Data>) -> Result<(), Error> {
+    let num1 = s!(stack.pop());
+    let bits = s!(stack.pop());
+    let Data::Integer(bits @ 0..=0xffff_ffff) = bits else {
+        error!(
+            shift,
+            format!(
+                "expected Integer from 0 to 2^32-1 as bits for {op}, found {}",
+                bits
+            )
+        );
+    };
+    let Some(int) = num1.as_big_int() else {
+        error!(shift, format!("expected Integer for {op}, found {}", num1));
+    };
+    if left && !int.is_zero() && bits as u64 + int.bits() > MAX_POW_BITS {
+        error!(
+            shift,
+            "arithmetic",
+            format!("result of {op} {num1} {bits} is over {MAX_POW_BITS} bits")
+        );
+    }
+    if let (Data::Integer(i1), false) = (&num1, left) {
+        stack.push(Data::Integer(i1 >> bits.min(63)));
+    } else if left {
+        stack.push(Data::integer(int.shl(bits as u32)));
+    } else {
+        stack.push(Data::integer(int.shr(bits as u32)));
+    }
+    Ok(())
+}
+
+/// One bits of the magnitude, `Popcount Neg 3` is 2.
+fn popcount(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let num1 = s!(stack.pop());
+    match (&num1, num1.as_big_int()) {
+        (Data::Integer(i1), _) => stack.push(Data::Integer(i1.unsigned_abs().count_ones() as i64)),
+        (_, Some(int)) => stack.push(Data::Integer(int.count_ones() as i64)),
+        _ => {
+            error!(popcount, format!("expected Integer, found {}", num1));
+        }
+    }
+    Ok(())
+}
+
 fn integer_unary_op(
     int_op: fn(i64) -> i64,
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let num1 = s!(stack.pop());
     match num1 {
         Data::Integer(i1) => stack.push(Data::Integer(int_op(i1))),
@@ -358,9 +1001,9 @@ fn _do(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let block = s!(stack.pop());
-    if let Data::Block(block) = (block) {
+    if let Data::Block(block) = block {
         block_exec.new_and_run(stack, prev_defs, &block)?;
     } else {
         error!(_do, format!("expect Block, found {}", block));
@@ -372,22 +1015,142 @@ fn _do_local(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let block = s!(stack.pop());
-    if let Data::Block(block) = (block) {
+    if let Data::Block(block) = block {
         block_exec.run_block(stack, prev_defs, block.block)?;
     } else {
         error!(_do_local, format!("expect Block, found {}", block));
     }
     Ok(())
 }
+/// Runs a loop body on the stack, false after `Break`.
+fn loop_body(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+    block: &data::Block,
+) -> Result<bool, Error> {
+    match block_exec.new_and_run(stack, prev_defs, block) {
+        Ok(()) | Err(Error::Continue) => Ok(true),
+        Err(Error::Break) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn loop_count(op: &str, data: Data) -> Result<i64, Error> {
+    let count = integer_arg(op, data)?;
+    if count < 0 {
+        error!(
+            loop_count,
+            format!("expect a count of 0 or more for {op}, found {count}")
+        );
+    }
+    Ok(count)
+}
+
+/// `While (condition) (body)`, both run on the stack and the condition leaves an Integer.
+fn _while(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let condition = block_arg("While", s!(stack.pop()))?;
+    let body = block_arg("While", s!(stack.pop()))?;
+    loop {
+        block_exec.new_and_run(stack, prev_defs, &condition)?;
+        match s!(stack.pop()) {
+            Data::Integer(0) => break,
+            Data::Integer(_) => {}
+            d => {
+                error!(
+                    _while,
+                    format!("expect Integer from While condition, found {}", d)
+                );
+            }
+        }
+        if !loop_body(stack, prev_defs, block_exec, &body)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `Repeat i (body)`, runs the body `i` times on the stack.
+fn repeat(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let count = loop_count("Repeat", s!(stack.pop()))?;
+    let body = block_arg("Repeat", s!(stack.pop()))?;
+    for _ in 0..count {
+        if !loop_body(stack, prev_defs, block_exec, &body)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `Times i (body)`, like `Repeat` with the run number from 0 pushed before each run.
+fn times(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let count = loop_count("Times", s!(stack.pop()))?;
+    let body = block_arg("Times", s!(stack.pop()))?;
+    for index in 0..count {
+        stack.push(Data::Integer(index));
+        if !loop_body(stack, prev_defs, block_exec, &body)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `For s l (body)`, runs the body on the stack for each element first to last,
+/// with the element defined as `s` in it.
+fn _for(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let name = string_arg("For", s!(stack.pop()))?;
+    let list = list_arg("For", s!(stack.pop()))?;
+    let mut body = block_arg("For", s!(stack.pop()))?;
+    for element in list {
+        body.captured_vars.insert(name.clone(), element);
+        if !loop_body(stack, prev_defs, block_exec, &body)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn _break(
+    _stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    Err(Error::Break)
+}
+
+fn _continue(
+    _stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    Err(Error::Continue)
+}
+
 fn get_fn(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let fn_name = s!(stack.pop());
-    if let Data::String(fn_name) = (fn_name) {
+    if let Data::String(fn_name) = fn_name {
         let func = s!(block_exec.get_data(&fn_name, prev_defs));
         if let Data::Fn(func) = func {
             stack.push(Data::Block(func));
@@ -407,9 +1170,9 @@ fn get_def(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let def_name = s!(stack.pop());
-    if let Data::String(def_name) = (def_name) {
+    if let Data::String(def_name) = def_name {
         let data = s!(block_exec.get_data(&def_name, prev_defs));
         stack.push(data);
     } else {
@@ -422,9 +1185,9 @@ fn has_def(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let def_name = s!(stack.pop());
-    if let Data::String(def_name) = (def_name) {
+    if let Data::String(def_name) = def_name {
         if block_exec.get_data(&def_name, prev_defs).is_some() {
             stack.push(Data::Integer(1));
         } else {
@@ -440,13 +1203,18 @@ fn timer(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let block = s!(stack.pop());
-    if let Data::Block(block) = (block) {
+    if let Data::Block(block) = block {
         let start = std::time::Instant::now();
         block_exec.new_and_run(stack, prev_defs, &block)?;
         let duration = std::time::Instant::now().duration_since(start);
-        println!("timer: {}s", duration.as_secs_f64());
+        writeln!(
+            block_exec.runtime.out.borrow_mut(),
+            "timer: {}s",
+            duration.as_secs_f64()
+        )
+        .str_res()?;
     } else {
         error!(timer, format!("expect Block, found {}", block));
     }
@@ -455,33 +1223,34 @@ fn timer(
 
 fn print(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
+    _prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let data = s!(stack.pop());
-    println!("{data}");
+    writeln!(block_exec.runtime.out.borrow_mut(), "{data}").str_res()?;
     Ok(())
 }
 
+#[allow(clippy::ptr_arg)]
 fn debug(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
+    _prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
-    print_stack(stack);
+) -> Result<(), Error> {
+    print_stack(stack, &mut **block_exec.runtime.out.borrow_mut()).str_res()?;
     Ok(())
 }
 
 fn assert(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let bool = s!(stack.pop());
     match bool {
         Data::Integer(bool) => {
             if bool == 0 {
-                error!(assert, "assert");
+                error!(assert, "assert", "expect non-zero Integer, found 0");
             }
         }
         _ => {
@@ -491,135 +1260,462 @@ fn assert(
     Ok(())
 }
 
-fn use_file(
+fn assert_eq(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
-    let file = s!(stack.pop());
-    if let Data::String(file) = (file) {
-        let mut file = std::fs::File::open(std::path::Path::new(&file)).str_res()?;
-        let mut source = String::new();
-        file.read_to_string(&mut source).str_res()?;
-        let mut scanner = crate::scanner::Scanner::new(&source);
-        let tokens = scanner.scan_tokens()?;
-        let mut parser = crate::parser::Parser::new(tokens);
-        let ast = parser.build_tree()?;
-        let block = crate::interpreter::run_tree::load(&ast);
-        let captured_vars = block_exec.capture(&block.capture_vars, prev_defs)?;
-        stack.push(Data::Block(crate::interpreter::data::Block {
-            block,
-            captured_vars,
-        }));
-    } else {
-        error!(use_file, format!("expect String, found {}", file));
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let expected = s!(stack.pop());
+    let actual = s!(stack.pop());
+    if expected != actual {
+        error!(
+            assert_eq,
+            "assert",
+            format!("expected {}, found {}", expected, actual)
+        );
     }
     Ok(())
 }
-fn try_use_file(
+
+fn assert_error(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
-    let file = s!(stack.pop());
-    if let Data::String(file) = (file) {
-        if let Ok(mut file) = std::fs::File::open(std::path::Path::new(&file)).str_res() {
-            let mut source = String::new();
-            if file.read_to_string(&mut source).str_res().is_ok() {
-                let mut scanner = crate::scanner::Scanner::new(&source);
-                if let Ok(tokens) = scanner.scan_tokens() {
-                    let mut parser = crate::parser::Parser::new(tokens);
-                    if let Ok(ast) = parser.build_tree() {
-                        let block = crate::interpreter::run_tree::load(&ast);
-                        if let Ok(captured_vars) =
-                            block_exec.capture(&block.capture_vars, prev_defs)
-                        {
-                            stack.push(Data::Block(crate::interpreter::data::Block {
-                                block,
-                                captured_vars,
-                            }));
-                            stack.push(Data::Integer(1));
-                            return Ok(());
-                        }
-                    }
-                }
+) -> Result<(), Error> {
+    let block = s!(stack.pop());
+    if let Data::Block(block) = block {
+        let mut block_stack = stack.clone();
+        match block_exec.new_and_run(&mut block_stack, prev_defs, &block) {
+            Err(Error::Raised(error)) => stack.push(error),
+            Err(abort) => return Err(abort),
+            Ok(()) => {
+                error!(assert_error, "assert", "expected error, block succeeded");
             }
         }
-        stack.push(Data::Integer(0));
     } else {
-        error!(try_use_file, format!("expect String, found {}", file));
+        error!(assert_error, format!("expect Block, found {}", block));
     }
     Ok(())
 }
 
-fn any_assert(
+fn test(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
+    _prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
-    if stack.len() == 0 {
-        error!(any_assert, "No data on stack");
+) -> Result<(), Error> {
+    let name = s!(stack.pop());
+    if let Data::String(name) = name {
+        let block = s!(stack.pop());
+        if let Data::Block(block) = block {
+            block_exec.runtime.tests.borrow_mut().push((name, block));
+        } else {
+            error!(test, format!("expect Block, found {}", block));
+        }
+    } else {
+        error!(test, format!("expect String, found {}", name));
     }
     Ok(())
 }
-fn any_check(
+
+fn throw(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let data = s!(stack.pop());
+    Err(Error::Raised(data))
+}
+
+/// Runs the block on a copy of the stack. On success the copy becomes the stack
+/// and 1 is pushed, on error the stack is left as before and the error and 0 are pushed.
+fn _try(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
-    if stack.len() == 0 {
-        stack.push(Data::Integer(0));
+) -> Result<(), Error> {
+    let block = s!(stack.pop());
+    if let Data::Block(block) = block {
+        let mut try_stack = stack.clone();
+        match block_exec.new_and_run(&mut try_stack, prev_defs, &block) {
+            Ok(()) => {
+                *stack = try_stack;
+                stack.push(Data::Integer(1));
+            }
+            Err(Error::Raised(error)) => {
+                stack.push(error);
+                stack.push(Data::Integer(0));
+            }
+            Err(abort) => return Err(abort),
+        }
     } else {
-        stack.push(Data::Integer(1));
+        error!(_try, format!("expect Block, found {}", block));
     }
     Ok(())
 }
-fn drop(
+
+/// Like `Try`, but on error runs the handler block with the error on top of the stack.
+fn catch(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
-    s!(stack.pop());
+) -> Result<(), Error> {
+    let block = s!(stack.pop());
+    if let Data::Block(block) = block {
+        let handler = s!(stack.pop());
+        if let Data::Block(handler) = handler {
+            let mut try_stack = stack.clone();
+            match block_exec.new_and_run(&mut try_stack, prev_defs, &block) {
+                Ok(()) => *stack = try_stack,
+                Err(Error::Raised(error)) => {
+                    stack.push(error);
+                    block_exec.new_and_run(stack, prev_defs, &handler)?;
+                }
+                Err(abort) => return Err(abort),
+            }
+        } else {
+            error!(catch, format!("expect Block, found {}", handler));
+        }
+    } else {
+        error!(catch, format!("expect Block, found {}", block));
+    }
     Ok(())
 }
-fn to_integer(
+
+fn new_error(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let kind = s!(stack.pop());
+    let message = s!(stack.pop());
+    if let (Data::String(kind), Data::String(message)) = (&kind, &message) {
+        stack.push(Data::Error(ErrorValue {
+            kind: kind.clone(),
+            message: message.clone(),
+            location: None,
+        }));
+    } else {
+        error!(
+            new_error,
+            format!("expect String String, found {} {}", kind, message)
+        );
+    }
+    Ok(())
+}
+
+fn error_kind(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let error = s!(stack.pop());
+    if let Data::Error(error) = error {
+        stack.push(Data::String(error.kind));
+    } else {
+        error!(error_kind, format!("expect Error, found {}", error));
+    }
+    Ok(())
+}
+
+fn error_message(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let error = s!(stack.pop());
+    if let Data::Error(error) = error {
+        stack.push(Data::String(error.message));
+    } else {
+        error!(error_message, format!("expect Error, found {}", error));
+    }
+    Ok(())
+}
+
+fn use_file(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let file = s!(stack.pop());
+    if let Data::String(file) = file {
+        let block = modules::use_file(block_exec, prev_defs, &file)?;
+        stack.push(Data::Block(block));
+    } else {
+        error!(use_file, format!("expect String, found {}", file));
+    }
+    Ok(())
+}
+fn try_use_file(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let file = s!(stack.pop());
+    if let Data::String(file) = file {
+        match modules::use_file(block_exec, prev_defs, &file) {
+            Ok(block) => {
+                stack.push(Data::Block(block));
+                stack.push(Data::Integer(1));
+            }
+            Err(Error::Abort(e)) => return Err(Error::Abort(e)),
+            Err(_) => stack.push(Data::Integer(0)),
+        }
+    } else {
+        error!(try_use_file, format!("expect String, found {}", file));
+    }
+    Ok(())
+}
+fn import(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
+    let name = s!(stack.pop());
+    let prefix = s!(stack.pop());
+    if let (Data::String(name), Data::String(prefix)) = (&name, &prefix) {
+        for (k, v) in modules::import(block_exec, prev_defs, name)? {
+            block_exec.defs.insert(format!("{prefix}{k}"), v);
+        }
+    } else {
+        error!(
+            import,
+            format!("expect String String, found {} {}", name, prefix)
+        );
+    }
+    Ok(())
+}
+fn import_only(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let name = s!(stack.pop());
+    let names = s!(stack.pop());
+    if let (Data::String(name), Data::List(names)) = (&name, &names) {
+        let mut exports = modules::import(block_exec, prev_defs, name)?;
+        for n in names {
+            let Data::String(n) = n else {
+                error!(import_only, format!("expect String in List, found {}", n));
+            };
+            let Some(data) = exports.remove(n) else {
+                error!(
+                    import_only,
+                    "import",
+                    format!("'{}' is not exported by {}", n, name)
+                );
+            };
+            block_exec.defs.insert(n.clone(), data);
+        }
+    } else {
+        error!(
+            import_only,
+            format!("expect String List, found {} {}", name, names)
+        );
+    }
+    Ok(())
+}
+fn export(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let name = s!(stack.pop());
+    if let Data::String(name) = name {
+        block_exec
+            .exports
+            .get_or_insert_with(HashSet::new)
+            .insert(name);
+    } else {
+        error!(export, format!("expect String, found {}", name));
+    }
+    Ok(())
+}
+
+#[allow(clippy::ptr_arg)]
+fn any_assert(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    if stack.is_empty() {
+        error!(any_assert, "No data on stack");
+    }
+    Ok(())
+}
+fn any_check(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    if stack.is_empty() {
+        stack.push(Data::Integer(0));
+    } else {
+        stack.push(Data::Integer(1));
+    }
+    Ok(())
+}
+fn drop(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    s!(stack.pop());
+    Ok(())
+}
+fn to_integer(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let d = s!(stack.pop());
     match d {
-        Data::String(d) => {
-            stack.push(Data::Integer(d.parse::<i64>().str_res()?));
+        Data::String(d) => match BigInt::parse(d.trim()) {
+            Some(int) => stack.push(Data::integer(int)),
+            None => {
+                error!(to_integer, format!("expect digits, found '{d}'"));
+            }
+        },
+        Data::Integer(_) | Data::BigInteger(_) => stack.push(d),
+        Data::Rational(rat) => match rat.numerator().checked_div(rat.denominator()) {
+            Some(int) => stack.push(Data::integer(int)),
+            None => {
+                error!(
+                    to_integer,
+                    "arithmetic",
+                    format!("{rat} has no Integer value")
+                );
+            }
+        },
+        Data::Decimal(dec) => match BigInt::from_f64(dec) {
+            Some(int) => stack.push(Data::integer(int)),
+            None => {
+                error!(
+                    to_integer,
+                    "arithmetic",
+                    format!("{dec} has no Integer value")
+                );
+            }
+        },
+        _ => {
+            error!(to_integer, format!("expect String or number, found {}", d));
         }
-        Data::Integer(d) => {stack.push(Data::Integer(d))}
-        Data::Decimal(d) => {stack.push(Data::Integer(d as i64))}
-        _ => {error!(to_integer, format!("expect String or Integer or Decimal, found {}", d));}
     }
     Ok(())
 }
 fn to_decimal(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let d = s!(stack.pop());
     match d {
         Data::String(d) => {
             stack.push(Data::Decimal(d.parse::<f64>().str_res()?));
         }
-        Data::Integer(d) => {stack.push(Data::Decimal(d as f64))}
-        Data::Decimal(d) => {stack.push(Data::Decimal(d))}
-        _ => {error!(to_decimal, format!("expect String or Integer or Decimal, found {}", d));}
+        Data::Integer(_) | Data::BigInteger(_) | Data::Rational(_) | Data::Decimal(_) => {
+            stack.push(Data::Decimal(d.as_f64().unwrap_or_default()))
+        }
+        _ => {
+            error!(to_decimal, format!("expect String or number, found {}", d));
+        }
+    }
+    Ok(())
+}
+
+/// Decimals become the shortest fraction that reads back as them, `0.1` is 1/10.
+fn to_rational(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let d = s!(stack.pop());
+    let rat = match &d {
+        Data::String(str) => Rational::parse(str.trim().trim_end_matches('r')),
+        Data::Decimal(dec) => Rational::from_f64(*dec),
+        _ => d.as_rational(),
+    };
+    match rat {
+        Some(rat) => stack.push(Data::Rational(Rc::new(rat))),
+        None => {
+            error!(
+                to_rational,
+                format!("expect String, number or finite Decimal, found {}", d)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `To-fixed places number`, a String with `places` digits after the point.
+/// Integers and rationals are rounded half away from zero.
+fn to_fixed(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let places = s!(stack.pop());
+    let d = s!(stack.pop());
+    let Data::Integer(places @ 0..=1000) = places else {
+        error!(
+            to_fixed,
+            format!("expect Integer from 0 to 1000 as places, found {}", places)
+        );
+    };
+    let fixed = match &d {
+        Data::Decimal(dec) => format!("{dec:.0$}", places as usize),
+        _ => match d.as_rational() {
+            Some(rat) => rat.to_fixed(places as u32),
+            None => {
+                error!(to_fixed, format!("expect number, found {}", d));
+            }
+        },
+    };
+    stack.push(Data::String(fixed));
+    Ok(())
+}
+
+/// Numerator of a Rational or Integer, the sign is kept here.
+fn numerator(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let d = s!(stack.pop());
+    match d.as_rational() {
+        Some(rat) => stack.push(Data::integer(rat.numerator().clone())),
+        None => {
+            error!(
+                numerator,
+                format!("expect Rational or Integer, found {}", d)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn denominator(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let d = s!(stack.pop());
+    match d.as_rational() {
+        Some(rat) => stack.push(Data::integer(rat.denominator().clone())),
+        None => {
+            error!(
+                denominator,
+                format!("expect Rational or Integer, found {}", d)
+            );
+        }
     }
     Ok(())
 }
 
 fn to_string(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let d = s!(stack.pop());
     stack.push(Data::String(d.to_string()));
     Ok(())
@@ -627,20 +1723,20 @@ fn to_string(
 
 fn to_list(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let d = s!(stack.pop());
     stack.push(Data::List(vec![d]));
     Ok(())
 }
 fn new_external(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let name = s!(stack.pop());
-    if let Data::String(name) = (name) {
+    if let Data::String(name) = name {
         let ext = crate::interpreter::external::new_external(name)?;
         stack.push(Data::External(ext));
     } else {
@@ -650,14 +1746,14 @@ fn new_external(
 }
 fn use_external(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let func = s!(stack.pop());
-    if let Data::String(func) = (func) {
+    if let Data::String(func) = func {
         let ext = s!(stack.pop());
-        if let Data::External(mut ext) = ext {
-            ext.borrow_mut().apply(func,stack)?;
+        if let Data::External(ext) = ext {
+            ext.borrow_mut().apply(func, stack)?;
         } else {
             error!(use_external, format!("expect External, found {}", ext));
         }
@@ -668,13 +1764,13 @@ fn use_external(
 }
 fn string_join(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let s1 = s!(stack.pop());
-    if let Data::String(s1) = (s1) {
+    if let Data::String(s1) = s1 {
         let s2 = s!(stack.pop());
-        if let Data::String(s2) = (s2) {
+        if let Data::String(s2) = s2 {
             stack.push(Data::String(s1.add(&s2)));
         } else {
             error!(string_join, format!("expect String, found {}", s2));
@@ -686,25 +1782,331 @@ fn string_join(
 }
 fn string_chars(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let s = s!(stack.pop());
-    if let Data::String(s) = (s) {
-        stack.push(Data::List(s.chars().rev().map(|c|Data::String(c.to_string())).collect()))
+    if let Data::String(s) = s {
+        stack.push(Data::List(
+            s.chars()
+                .rev()
+                .map(|c| Data::String(c.to_string()))
+                .collect(),
+        ))
     } else {
         error!(string_chars, format!("expect String, found {}", s));
     }
     Ok(())
 }
 
+fn string_arg(op: &str, data: Data) -> Result<String, Error> {
+    match data {
+        Data::String(str) => Ok(str),
+        d => {
+            error!(string_arg, format!("expect String for {op}, found {}", d));
+        }
+    }
+}
+
+fn integer_arg(op: &str, data: Data) -> Result<i64, Error> {
+    match data {
+        Data::Integer(int) => Ok(int),
+        d => {
+            error!(integer_arg, format!("expect Integer for {op}, found {}", d));
+        }
+    }
+}
+
+/// Byte offset of char `index`, the length past the last char.
+fn char_offset(str: &str, index: usize) -> usize {
+    str.char_indices()
+        .nth(index)
+        .map_or(str.len(), |(offset, _)| offset)
+}
+
+/// Length in chars (code points), not bytes or grapheme clusters.
+fn string_len(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let str = string_arg("String-len", s!(stack.pop()))?;
+    stack.push(Data::Integer(str.chars().count() as i64));
+    Ok(())
+}
+
+/// `String-split s separator`, an empty separator splits into chars.
+fn string_split(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let str = string_arg("String-split", s!(stack.pop()))?;
+    let separator = string_arg("String-split", s!(stack.pop()))?;
+    let parts: Vec<_> = if separator.is_empty() {
+        str.chars().map(|c| Data::String(c.to_string())).collect()
+    } else {
+        str.split(&separator)
+            .map(|part| Data::String(part.to_string()))
+            .collect()
+    };
+    stack.push(Data::List(parts.into_iter().rev().collect()));
+    Ok(())
+}
+
+/// `String-slice s start end`, chars from `start` up to but without `end`.
+/// Negative indexes count from the end, indexes past either end are clamped.
+fn string_slice(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let str = string_arg("String-slice", s!(stack.pop()))?;
+    let start = integer_arg("String-slice", s!(stack.pop()))?;
+    let end = integer_arg("String-slice", s!(stack.pop()))?;
+    let len = str.chars().count() as i64;
+    let clamp = |index: i64| if index < 0 { (len + index).max(0) } else { index.min(len) } as usize;
+    let (start, end) = (clamp(start), clamp(end));
+    let slice = if start < end {
+        &str[char_offset(&str, start)..char_offset(&str, end)]
+    } else {
+        ""
+    };
+    stack.push(Data::String(slice.to_string()));
+    Ok(())
+}
+
+/// `String-find s part`, char index of the first `part` or -1.
+fn string_find(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let str = string_arg("String-find", s!(stack.pop()))?;
+    let part = string_arg("String-find", s!(stack.pop()))?;
+    let index = str
+        .find(&part)
+        .map_or(-1, |offset| str[..offset].chars().count() as i64);
+    stack.push(Data::Integer(index));
+    Ok(())
+}
+
+/// `String-replace s from to`, every `from`.
+fn string_replace(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let str = string_arg("String-replace", s!(stack.pop()))?;
+    let from = string_arg("String-replace", s!(stack.pop()))?;
+    let to = string_arg("String-replace", s!(stack.pop()))?;
+    if from.is_empty() {
+        error!(string_replace, "expect a non-empty String to replace");
+    }
+    stack.push(Data::String(str.replace(&from, &to)));
+    Ok(())
+}
+
+fn string_map(op: &str, map: fn(&str) -> String, stack: &mut Vec<Data>) -> Result<(), Error> {
+    let str = string_arg(op, s!(stack.pop()))?;
+    stack.push(Data::String(map(&str)));
+    Ok(())
+}
+
+fn string_test(op: &str, test: fn(&str, &str) -> bool, stack: &mut Vec<Data>) -> Result<(), Error> {
+    let str = string_arg(op, s!(stack.pop()))?;
+    let part = string_arg(op, s!(stack.pop()))?;
+    stack.push(Data::Integer(test(&str, &part) as i64));
+    Ok(())
+}
+
+/// Largest String in bytes `String-repeat` makes.
+const MAX_REPEAT_LEN: usize = 1 << 28;
+
+/// `String-repeat s n`.
+fn string_repeat(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let str = string_arg("String-repeat", s!(stack.pop()))?;
+    let times = integer_arg("String-repeat", s!(stack.pop()))?;
+    if times < 0 {
+        error!(
+            string_repeat,
+            format!("expect a non-negative count, found {times}")
+        );
+    }
+    match str.len().checked_mul(times as usize) {
+        Some(len) if len <= MAX_REPEAT_LEN => stack.push(Data::String(str.repeat(times as usize))),
+        _ => {
+            error!(
+                string_repeat,
+                format!(
+                    "String-repeat {times} is longer than the maximum of {MAX_REPEAT_LEN} bytes"
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Code point of a String of one char.
+fn char_code(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let str = string_arg("Char-code", s!(stack.pop()))?;
+    let mut chars = str.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => stack.push(Data::Integer(c as i64)),
+        _ => {
+            error!(
+                char_code,
+                format!("expect a String of one char, found '{str}'")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn code_char(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let code = integer_arg("Code-char", s!(stack.pop()))?;
+    match u32::try_from(code).ok().and_then(char::from_u32) {
+        Some(c) => stack.push(Data::String(c.to_string())),
+        None => {
+            error!(code_char, format!("{code} is not a Unicode scalar value"));
+        }
+    }
+    Ok(())
+}
+
+/// `Format template values...`, one value per placeholder index, the first popped is `{0}`.
+fn format(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let template = string_arg("Format", s!(stack.pop()))?;
+    let parsed = match Template::parse(&template) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            error!(format, e);
+        }
+    };
+    let count = parsed.value_count();
+    if stack.len() < count {
+        error!(
+            format,
+            "stack",
+            format!(
+                "template '{template}' takes {count} values, the stack has {}",
+                stack.len()
+            )
+        );
+    }
+    let values: Vec<_> = stack.drain(stack.len() - count..).rev().collect();
+    match parsed.render(&values) {
+        Ok(str) => stack.push(Data::String(str)),
+        Err(e) => {
+            error!(format, e);
+        }
+    }
+    Ok(())
+}
+
+/// Pops the text and then the pattern, compiled patterns are cached by the runtime.
+fn regex_args(
+    op: &str,
+    stack: &mut Vec<Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(String, Rc<Regex>), Error> {
+    let text = string_arg(op, s!(stack.pop()))?;
+    let pattern = string_arg(op, s!(stack.pop()))?;
+    match block_exec.runtime.regex(&pattern) {
+        Ok(regex) => Ok((text, regex)),
+        Err(e) => {
+            error!(regex_args, format!("bad pattern '{pattern}' for {op}: {e}"));
+        }
+    }
+}
+
+/// The whole match and then every group, a group that took no part is an empty String.
+fn captures_list(captures: &Captures, chars: &[char]) -> Data {
+    let strings = captures.iter().map(|capture| {
+        let (begin, end) = capture.unwrap_or((0, 0));
+        Data::String(chars[begin..end].iter().collect())
+    });
+    Data::List(strings.rev().collect())
+}
+
+/// `Regex-match? s pattern`, whether the pattern matches anywhere in `s`.
+fn regex_match(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let (text, regex) = regex_args("Regex-match?", stack, block_exec)?;
+    stack.push(Data::Integer(regex.is_match(&text) as i64));
+    Ok(())
+}
+
+/// `Regex-find s pattern`, captures of the first match or an empty List.
+fn regex_find(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let (text, regex) = regex_args("Regex-find", stack, block_exec)?;
+    let chars: Vec<char> = text.chars().collect();
+    match regex.captures_at(&chars, 0) {
+        Some(captures) => stack.push(captures_list(&captures, &chars)),
+        None => stack.push(Data::List(vec![])),
+    }
+    Ok(())
+}
+
+/// `Regex-find-all s pattern`, a List with the captures of every match.
+fn regex_find_all(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let (text, regex) = regex_args("Regex-find-all", stack, block_exec)?;
+    let chars: Vec<char> = text.chars().collect();
+    let all = regex.captures_all(&chars);
+    let matches = all
+        .iter()
+        .rev()
+        .map(|captures| captures_list(captures, &chars));
+    stack.push(Data::List(matches.collect()));
+    Ok(())
+}
+
+/// `Regex-replace s pattern replacement`, `$1` in the replacement is a group.
+fn regex_replace(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let (text, regex) = regex_args("Regex-replace", stack, block_exec)?;
+    let replacement = string_arg("Regex-replace", s!(stack.pop()))?;
+    stack.push(Data::String(regex.replace_all(&text, &replacement)));
+    Ok(())
+}
+
 fn list(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let block = s!(stack.pop());
-    if let Data::Block(block) = (block) {
+    if let Data::Block(block) = block {
         let mut new_stack = Vec::new();
         block_exec.new_and_run(&mut new_stack, prev_defs, &block)?;
         stack.push(Data::List(new_stack));
@@ -714,14 +2116,13 @@ fn list(
     Ok(())
 }
 
-
 fn list_reverse(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let list = s!(stack.pop());
-    if let Data::List(mut list) = (list) {
+    if let Data::List(mut list) = list {
         list.reverse();
         stack.push(Data::List(list));
     } else {
@@ -730,26 +2131,538 @@ fn list_reverse(
     Ok(())
 }
 
+/// Elements of a List in order, the first is the one written first.
+fn list_arg(op: &str, data: Data) -> Result<Vec<Data>, Error> {
+    match data {
+        Data::List(mut list) => {
+            list.reverse();
+            Ok(list)
+        }
+        d => {
+            error!(list_arg, format!("expect List for {op}, found {}", d));
+        }
+    }
+}
+
+fn block_arg(op: &str, data: Data) -> Result<data::Block, Error> {
+    match data {
+        Data::Block(block) => Ok(block),
+        d => {
+            error!(block_arg, format!("expect Block for {op}, found {}", d));
+        }
+    }
+}
 
-fn list_stack(
+/// A List from elements in order.
+fn list_of(mut elements: Vec<Data>) -> Data {
+    elements.reverse();
+    Data::List(elements)
+}
+
+/// Runs `block` on its own stack holding `args`, the last on top, and checks that it leaves
+/// exactly one value.
+fn call_block(
+    op: &str,
+    block: &data::Block,
+    args: Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<Data, Error> {
+    let mut block_stack = args;
+    block_exec.new_and_run(&mut block_stack, prev_defs, block)?;
+    if block_stack.len() != 1 {
+        error!(
+            call_block,
+            "stack",
+            format!(
+                "{op} block must leave 1 value, left {}",
+                Data::List(block_stack)
+            )
+        );
+    }
+    Ok(s!(block_stack.pop()))
+}
+
+fn call_predicate(
+    op: &str,
+    block: &data::Block,
+    element: Data,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<bool, Error> {
+    match call_block(op, block, vec![element], prev_defs, block_exec)? {
+        Data::Integer(bool) => Ok(bool != 0),
+        d => {
+            error!(
+                call_predicate,
+                format!("{op} block must leave an Integer, left {}", d)
+            );
+        }
+    }
+}
+
+/// `Map l (block)`, the block gets each element alone on its stack and leaves its replacement.
+fn map(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Map", s!(stack.pop()))?;
+    let block = block_arg("Map", s!(stack.pop()))?;
+    let mut mapped = Vec::with_capacity(list.len());
+    for element in list {
+        mapped.push(call_block(
+            "Map",
+            &block,
+            vec![element],
+            prev_defs,
+            block_exec,
+        )?);
+    }
+    stack.push(list_of(mapped));
+    Ok(())
+}
+
+/// `Filter l (block)`, keeps the elements for which the block leaves a true Integer.
+fn filter(
     stack: &mut Vec<Data>,
     prev_defs: &HashMap<String, Data>,
     block_exec: &mut BlockExec,
-) -> Result<(), String> {
+) -> Result<(), Error> {
+    let list = list_arg("Filter", s!(stack.pop()))?;
+    let block = block_arg("Filter", s!(stack.pop()))?;
+    let mut kept = vec![];
+    for element in list {
+        if call_predicate("Filter", &block, element.clone(), prev_defs, block_exec)? {
+            kept.push(element);
+        }
+    }
+    stack.push(list_of(kept));
+    Ok(())
+}
+
+/// Runs `block` with the accumulator on top of each element, like `+ acc element`.
+fn fold_elements(
+    op: &str,
+    block: &data::Block,
+    init: Data,
+    elements: impl Iterator<Item = Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<Data, Error> {
+    let mut acc = init;
+    for element in elements {
+        acc = call_block(op, block, vec![element, acc], prev_defs, block_exec)?;
+    }
+    Ok(acc)
+}
+
+/// `Fold l init (block)`.
+fn fold(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Fold", s!(stack.pop()))?;
+    let init = s!(stack.pop());
+    let block = block_arg("Fold", s!(stack.pop()))?;
+    let acc = fold_elements(
+        "Fold",
+        &block,
+        init,
+        list.into_iter(),
+        prev_defs,
+        block_exec,
+    )?;
+    stack.push(acc);
+    Ok(())
+}
+
+/// `Reduce l (block)`, a Fold starting with the first element.
+fn reduce(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Reduce", s!(stack.pop()))?;
+    let block = block_arg("Reduce", s!(stack.pop()))?;
+    let mut elements = list.into_iter();
+    let Some(first) = elements.next() else {
+        error!(reduce, "expect a non-empty List for Reduce");
+    };
+    let acc = fold_elements("Reduce", &block, first, elements, prev_defs, block_exec)?;
+    stack.push(acc);
+    Ok(())
+}
+
+/// `Each l (block)`, the block gets each element alone on its stack and must leave nothing.
+fn each(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Each", s!(stack.pop()))?;
+    let block = block_arg("Each", s!(stack.pop()))?;
+    for element in list {
+        let mut block_stack = vec![element];
+        block_exec.new_and_run(&mut block_stack, prev_defs, &block)?;
+        if !block_stack.is_empty() {
+            error!(
+                each,
+                "stack",
+                format!(
+                    "Each block must leave nothing, left {}",
+                    Data::List(block_stack)
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `Any-of? l (block)` and `All-of? l (block)`, stop at the first element deciding the result.
+fn any_all(
+    op: &str,
+    any: bool,
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg(op, s!(stack.pop()))?;
+    let block = block_arg(op, s!(stack.pop()))?;
+    for element in list {
+        if call_predicate(op, &block, element, prev_defs, block_exec)? == any {
+            stack.push(Data::Integer(any as i64));
+            return Ok(());
+        }
+    }
+    stack.push(Data::Integer(!any as i64));
+    Ok(())
+}
+
+/// `Zip l1 l2`, Lists of 2 elements, as long as the shorter List.
+fn zip(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list1 = list_arg("Zip", s!(stack.pop()))?;
+    let list2 = list_arg("Zip", s!(stack.pop()))?;
+    let pairs = list1
+        .into_iter()
+        .zip(list2)
+        .map(|(d1, d2)| list_of(vec![d1, d2]));
+    stack.push(list_of(pairs.collect()));
+    Ok(())
+}
+
+/// `Flat-map l (block)`, the block leaves a List for each element and the Lists are joined.
+fn flat_map(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Flat-map", s!(stack.pop()))?;
+    let block = block_arg("Flat-map", s!(stack.pop()))?;
+    let mut joined = vec![];
+    for element in list {
+        let mapped = call_block("Flat-map", &block, vec![element], prev_defs, block_exec)?;
+        joined.extend(list_arg("Flat-map", mapped)?);
+    }
+    stack.push(list_of(joined));
+    Ok(())
+}
+
+/// Position of `index` in a List of `len` elements, negative indexes count from the end.
+fn element_index(op: &str, index: i64, len: usize) -> Result<usize, Error> {
+    let position = if index < 0 { index + len as i64 } else { index };
+    if position < 0 || position >= len as i64 {
+        error!(
+            element_index,
+            format!("index {index} out of range for {op} on a List of {len}")
+        );
+    }
+    Ok(position as usize)
+}
+
+/// `List-get l i`, the first element is 0 and the last -1.
+fn list_get(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let mut list = list_arg("List-get", s!(stack.pop()))?;
+    let index = integer_arg("List-get", s!(stack.pop()))?;
+    let position = element_index("List-get", index, list.len())?;
+    stack.push(list.swap_remove(position));
+    Ok(())
+}
+
+/// `List-set l i a`, a copy of the List with element `i` replaced.
+fn list_set(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let mut list = list_arg("List-set", s!(stack.pop()))?;
+    let index = integer_arg("List-set", s!(stack.pop()))?;
+    let element = s!(stack.pop());
+    let position = element_index("List-set", index, list.len())?;
+    list[position] = element;
+    stack.push(list_of(list));
+    Ok(())
+}
+
+/// `List-slice l start end`, indexes like `String-slice`.
+fn list_slice(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("List-slice", s!(stack.pop()))?;
+    let start = integer_arg("List-slice", s!(stack.pop()))?;
+    let end = integer_arg("List-slice", s!(stack.pop()))?;
+    let len = list.len() as i64;
+    let clamp = |index: i64| if index < 0 { (len + index).max(0) } else { index.min(len) } as usize;
+    let (start, end) = (clamp(start), clamp(end));
+    let slice = if start < end {
+        list[start..end].to_vec()
+    } else {
+        vec![]
+    };
+    stack.push(list_of(slice));
+    Ok(())
+}
+
+/// `List-concat l1 l2`, the elements of `l1` and then of `l2`.
+fn list_concat(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let mut list = list_arg("List-concat", s!(stack.pop()))?;
+    list.extend(list_arg("List-concat", s!(stack.pop()))?);
+    stack.push(list_of(list));
+    Ok(())
+}
+
+/// `List-index-of l a`, index of the first element `==` to `a` or -1.
+fn list_index_of(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("List-index-of", s!(stack.pop()))?;
+    let element = s!(stack.pop());
+    let index = list.iter().position(|d| d.equal(&element));
+    stack.push(Data::Integer(index.map_or(-1, |i| i as i64)));
+    Ok(())
+}
+
+fn list_contains(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("List-contains?", s!(stack.pop()))?;
+    let element = s!(stack.pop());
+    stack.push(Data::Integer(list.iter().any(|d| d.equal(&element)) as i64));
+    Ok(())
+}
+
+/// Stable merge sort, stops at the first error of `compare`.
+fn merge_sort<T: Clone>(
+    items: &mut [T],
+    compare: &mut dyn FnMut(&T, &T) -> Result<Ordering, Error>,
+) -> Result<(), Error> {
+    if items.len() <= 1 {
+        return Ok(());
+    }
+    let middle = items.len() / 2;
+    merge_sort(&mut items[..middle], compare)?;
+    merge_sort(&mut items[middle..], compare)?;
+    let (left, right) = (items[..middle].to_vec(), items[middle..].to_vec());
+    let (mut l, mut r) = (0, 0);
+    for slot in items.iter_mut() {
+        let take_left = r == right.len()
+            || (l < left.len() && compare(&left[l], &right[r])? != Ordering::Greater);
+        if take_left {
+            *slot = left[l].clone();
+            l += 1;
+        } else {
+            *slot = right[r].clone();
+            r += 1;
+        }
+    }
+    Ok(())
+}
+
+fn compare_sort(op: &str, d1: &Data, d2: &Data) -> Result<Ordering, Error> {
+    match d1.compare(d2) {
+        Some(ordering) => Ok(ordering),
+        None => {
+            error!(
+                compare_sort,
+                format!("can not order {} and {} for {op}", d1, d2)
+            );
+        }
+    }
+}
+
+/// `Sort l`, ascending in the order of `<`.
+fn sort(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let mut list = list_arg("Sort", s!(stack.pop()))?;
+    merge_sort(&mut list, &mut |d1, d2| compare_sort("Sort", d1, d2))?;
+    stack.push(list_of(list));
+    Ok(())
+}
+
+/// `Sort-by l (block)`, ascending by the key the block leaves for each element,
+/// the block runs once per element.
+fn sort_by(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Sort-by", s!(stack.pop()))?;
+    let block = block_arg("Sort-by", s!(stack.pop()))?;
+    let mut keyed = Vec::with_capacity(list.len());
+    for element in list {
+        let key = call_block(
+            "Sort-by",
+            &block,
+            vec![element.clone()],
+            prev_defs,
+            block_exec,
+        )?;
+        keyed.push((key, element));
+    }
+    merge_sort(&mut keyed, &mut |(k1, _), (k2, _)| {
+        compare_sort("Sort-by", k1, k2)
+    })?;
+    stack.push(list_of(
+        keyed.into_iter().map(|(_, element)| element).collect(),
+    ));
+    Ok(())
+}
+
+/// `Sort-with l (block)`, the block gets two elements, the first on top, and leaves
+/// -1, 0 or 1 like `Compare`.
+fn sort_with(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let mut list = list_arg("Sort-with", s!(stack.pop()))?;
+    let block = block_arg("Sort-with", s!(stack.pop()))?;
+    merge_sort(&mut list, &mut |d1, d2| {
+        let args = vec![d2.clone(), d1.clone()];
+        match call_block("Sort-with", &block, args, prev_defs, block_exec)? {
+            Data::Integer(int) => Ok(int.cmp(&0)),
+            d => {
+                error!(
+                    sort_with,
+                    format!("Sort-with block must leave an Integer, left {}", d)
+                );
+            }
+        }
+    })?;
+    stack.push(list_of(list));
+    Ok(())
+}
+
+/// `Unique l`, the first of the elements `==` to each other.
+fn unique(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Unique", s!(stack.pop()))?;
+    let mut kept: Vec<Data> = vec![];
+    for element in list {
+        if !kept.iter().any(|d| d.equal(&element)) {
+            kept.push(element);
+        }
+    }
+    stack.push(list_of(kept));
+    Ok(())
+}
+
+/// `Group-by l (block)`, a List of `List ( key elements )` for every key the block leaves,
+/// keys in the order they first appear and elements in their order.
+fn group_by(
+    stack: &mut Vec<Data>,
+    prev_defs: &HashMap<String, Data>,
+    block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let list = list_arg("Group-by", s!(stack.pop()))?;
+    let block = block_arg("Group-by", s!(stack.pop()))?;
+    let mut groups: Vec<(Data, Vec<Data>)> = vec![];
+    for element in list {
+        let key = call_block(
+            "Group-by",
+            &block,
+            vec![element.clone()],
+            prev_defs,
+            block_exec,
+        )?;
+        match groups.iter_mut().find(|(k, _)| k.equal(&key)) {
+            Some((_, elements)) => elements.push(element),
+            None => groups.push((key, vec![element])),
+        }
+    }
+    let groups = groups
+        .into_iter()
+        .map(|(key, elements)| list_of(vec![key, list_of(elements)]));
+    stack.push(list_of(groups.collect()));
+    Ok(())
+}
+
+/// Largest List `Range` makes.
+const MAX_RANGE_LEN: i128 = 1 << 24;
+
+/// `Range start end`, the Integers from `start` up to but not including `end`.
+fn range(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
+    let start = integer_arg("Range", s!(stack.pop()))?;
+    let end = integer_arg("Range", s!(stack.pop()))?;
+    if end as i128 - start as i128 > MAX_RANGE_LEN {
+        error!(
+            range,
+            format!("Range {start} {end} is longer than the maximum of {MAX_RANGE_LEN}")
+        );
+    }
+    stack.push(list_of((start..end).map(Data::Integer).collect()));
+    Ok(())
+}
+
+fn list_stack(
+    stack: &mut Vec<Data>,
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let mut list = Vec::new();
-    mem::swap(stack,&mut list);
+    mem::swap(stack, &mut list);
     stack.push(Data::List(list));
     Ok(())
 }
 
+#[allow(dead_code)]
 fn list_swap_stack(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let list = s!(stack.pop());
     if let Data::List(mut list) = list {
-        mem::swap(stack,&mut list);
+        mem::swap(stack, &mut list);
         stack.push(Data::List(list));
     } else {
         error!(list_to_stack, format!("expect List, found {}", list));
@@ -757,14 +2670,14 @@ fn list_swap_stack(
     Ok(())
 }
 
-
+#[allow(dead_code)]
 fn list_to_stack(
     stack: &mut Vec<Data>,
-    prev_defs: &HashMap<String, Data>,
-    block_exec: &mut BlockExec,
-) -> Result<(), String> {
+    _prev_defs: &HashMap<String, Data>,
+    _block_exec: &mut BlockExec,
+) -> Result<(), Error> {
     let list = s!(stack.pop());
-    if let Data::List(mut list) = list {
+    if let Data::List(list) = list {
         for data in list.into_iter().rev() {
             stack.push(data);
         }
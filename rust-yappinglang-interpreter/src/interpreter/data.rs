@@ -1,16 +1,23 @@
+use crate::bigint::BigInt;
+use crate::interpreter::external::External;
 use crate::interpreter::run_tree;
 use crate::interpreter::StackFn;
-use std::any::type_name_of_val;
-use std::cell::{Cell, RefCell, UnsafeCell};
+use crate::rational::Rational;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter, Pointer};
+use std::fmt::{Display, Formatter};
 use std::rc::Rc;
-use crate::interpreter::external::External;
 
+#[allow(dead_code)]
 #[derive(Clone)]
 pub enum Data {
     String(String),
     Integer(i64),
+    /// Integer outside the `i64` range, made by `Data::integer`.
+    BigInteger(Rc<BigInt>),
+    /// Exact fraction, only made from other numbers on request.
+    Rational(Rc<Rational>),
     Decimal(f64),
     List(Vec<Data>),
     Dict(HashMap<Data, Data>),
@@ -19,6 +26,23 @@ pub enum Data {
     Fn(Block),
     External(Rc<RefCell<dyn External>>),
     BuiltinFunc(StackFn),
+    Error(ErrorValue),
+}
+
+/// Raised by a failing builtin, `kind` is `type`, `stack`, `undefined`, `assert`, `import`,
+/// `arithmetic` or `error`.
+/// `location` is the file and line where it was raised, not part of equality.
+#[derive(Clone)]
+pub struct ErrorValue {
+    pub kind: String,
+    pub message: String,
+    pub location: Option<(Rc<str>, u64)>,
+}
+
+impl PartialEq for ErrorValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.message == other.message
+    }
 }
 
 #[derive(Clone)]
@@ -27,11 +51,192 @@ pub struct Block {
     pub captured_vars: HashMap<String, Data>,
 }
 
+impl Data {
+    /// Name used by the type builtins, e.g. `Integer` of `Integer?`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Data::String(_) => "String",
+            Data::Integer(_) | Data::BigInteger(_) => "Integer",
+            Data::Rational(_) => "Rational",
+            Data::Decimal(_) => "Decimal",
+            Data::List(_) => "List",
+            Data::Dict(_) => "Dict",
+            Data::Box(_) => "Box",
+            Data::Block(_) => "Block",
+            Data::Fn(_) => "Fn",
+            Data::External(_) => "External",
+            Data::BuiltinFunc(_) => "Built-in",
+            Data::Error(_) => "Error",
+        }
+    }
+
+    /// Integer of any size, `BigInteger` only when `int` does not fit in `i64`.
+    pub fn integer(int: BigInt) -> Data {
+        match int.to_i64() {
+            Some(int) => Data::Integer(int),
+            None => Data::BigInteger(Rc::new(int)),
+        }
+    }
+
+    pub fn as_big_int(&self) -> Option<BigInt> {
+        match self {
+            Data::Integer(int) => Some(BigInt::from(*int)),
+            Data::BigInteger(int) => Some(int.as_ref().clone()),
+            _ => None,
+        }
+    }
+
+    /// Integers and rationals.
+    pub fn as_rational(&self) -> Option<Rational> {
+        match self {
+            Data::Rational(rat) => Some(rat.as_ref().clone()),
+            _ => Some(Rational::from(self.as_big_int()?)),
+        }
+    }
+
+    /// Numbers as `f64`, rounded when needed.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Data::Integer(int) => Some(*int as f64),
+            Data::BigInteger(int) => Some(int.to_f64()),
+            Data::Rational(rat) => Some(rat.to_f64()),
+            Data::Decimal(dec) => Some(*dec),
+            _ => None,
+        }
+    }
+
+    /// Equality of `==`: like `PartialEq`, but Integer and Decimal compare by value,
+    /// also inside lists and dicts.
+    pub fn equal(&self, other: &Data) -> bool {
+        match (self, other) {
+            (Data::Integer(_) | Data::BigInteger(_) | Data::Rational(_), Data::Decimal(_))
+            | (Data::Decimal(_), Data::Integer(_) | Data::BigInteger(_) | Data::Rational(_))
+            | (Data::Integer(_) | Data::BigInteger(_), Data::Rational(_))
+            | (Data::Rational(_), Data::Integer(_) | Data::BigInteger(_)) => {
+                self.compare(other) == Some(Ordering::Equal)
+            }
+            (Data::List(l1), Data::List(l2)) => {
+                l1.len() == l2.len() && l1.iter().zip(l2).all(|(d1, d2)| d1.equal(d2))
+            }
+            (Data::Dict(d1), Data::Dict(d2)) => {
+                d1.len() == d2.len()
+                    && d1
+                        .iter()
+                        .all(|(k1, v1)| d2.iter().any(|(k2, v2)| k1 == k2 && v1.equal(v2)))
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Order of `<` and friends. Numbers by value, strings by code points, lists element by element.
+    /// Rationals are compared with decimals as decimals, like in arithmetic.
+    /// `None` for other kinds, different kinds and NaN.
+    pub fn compare(&self, other: &Data) -> Option<Ordering> {
+        match (self, other) {
+            (Data::Integer(i1), Data::Integer(i2)) => Some(i1.cmp(i2)),
+            (Data::Decimal(d1), Data::Decimal(d2)) => d1.partial_cmp(d2),
+            (Data::Integer(i), Data::Decimal(d)) => compare_int_dec(*i, *d),
+            (Data::Decimal(d), Data::Integer(i)) => compare_int_dec(*i, *d).map(Ordering::reverse),
+            (Data::BigInteger(b), Data::Decimal(d)) => compare_big_dec(b, *d),
+            (Data::Decimal(d), Data::BigInteger(b)) => {
+                compare_big_dec(b, *d).map(Ordering::reverse)
+            }
+            (Data::Integer(_) | Data::BigInteger(_), Data::Integer(_) | Data::BigInteger(_)) => {
+                Some(self.as_big_int()?.cmp(&other.as_big_int()?))
+            }
+            (Data::Rational(r), Data::Decimal(d)) => r.to_f64().partial_cmp(d),
+            (Data::Decimal(d), Data::Rational(r)) => d.partial_cmp(&r.to_f64()),
+            (Data::Rational(_), _) | (_, Data::Rational(_)) => {
+                Some(self.as_rational()?.cmp(&other.as_rational()?))
+            }
+            (Data::String(s1), Data::String(s2)) => Some(s1.cmp(s2)),
+            (Data::List(l1), Data::List(l2)) => {
+                // Lists are stored last element first.
+                for (d1, d2) in l1.iter().rev().zip(l2.iter().rev()) {
+                    match d1.compare(d2)? {
+                        Ordering::Equal => {}
+                        ordering => return Some(ordering),
+                    }
+                }
+                Some(l1.len().cmp(&l2.len()))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn compare_big_dec(b: &BigInt, d: f64) -> Option<Ordering> {
+    if d.is_nan() {
+        return None;
+    }
+    let Some(whole) = BigInt::from_f64(d) else {
+        // Infinite.
+        return Some(if d > 0.0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        });
+    };
+    match b.cmp(&whole) {
+        Ordering::Equal => 0.0.partial_cmp(&(d - d.trunc())),
+        ordering => Some(ordering),
+    }
+}
+
+/// Exact, `i as f64` would round large integers.
+fn compare_int_dec(i: i64, d: f64) -> Option<Ordering> {
+    if d.is_nan() {
+        return None;
+    }
+    if d >= 9223372036854775808.0 {
+        return Some(Ordering::Less);
+    }
+    if d < -9223372036854775808.0 {
+        return Some(Ordering::Greater);
+    }
+    let whole = d.trunc();
+    match i.cmp(&(whole as i64)) {
+        Ordering::Equal => 0.0.partial_cmp(&(d - whole)),
+        ordering => Some(ordering),
+    }
+}
+
+/// Structural equality, Integer and Decimal are different kinds.
+/// Boxes, blocks and externals are equal only to themselves.
+impl PartialEq for Data {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Data::String(s1), Data::String(s2)) => s1 == s2,
+            (Data::Integer(i1), Data::Integer(i2)) => i1 == i2,
+            (Data::BigInteger(i1), Data::BigInteger(i2)) => i1 == i2,
+            (Data::Rational(r1), Data::Rational(r2)) => r1 == r2,
+            (Data::Decimal(d1), Data::Decimal(d2)) => d1 == d2,
+            (Data::List(l1), Data::List(l2)) => l1 == l2,
+            (Data::Dict(d1), Data::Dict(d2)) => {
+                d1.len() == d2.len()
+                    && d1
+                        .iter()
+                        .all(|(k1, v1)| d2.iter().any(|(k2, v2)| k1 == k2 && v1 == v2))
+            }
+            (Data::Box(b1), Data::Box(b2)) => Rc::ptr_eq(b1, b2),
+            (Data::Block(b1), Data::Block(b2)) | (Data::Fn(b1), Data::Fn(b2)) => {
+                Rc::ptr_eq(&b1.block, &b2.block) && b1.captured_vars == b2.captured_vars
+            }
+            (Data::External(e1), Data::External(e2)) => Rc::ptr_eq(e1, e2),
+            (Data::BuiltinFunc(f1), Data::BuiltinFunc(f2)) => std::ptr::fn_addr_eq(*f1, *f2),
+            (Data::Error(e1), Data::Error(e2)) => e1 == e2,
+            _ => false,
+        }
+    }
+}
+
 impl Display for Data {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Data::String(str) => f.write_fmt(format_args!("'{str}'")),
             Data::Integer(int) => f.write_fmt(format_args!("{int}")),
+            Data::BigInteger(int) => f.write_fmt(format_args!("{int}")),
+            Data::Rational(rat) => f.write_fmt(format_args!("{rat}")),
             Data::Decimal(dec) => f.write_fmt(format_args!("{dec}f")),
             Data::List(l) => {
                 f.write_fmt(format_args!("[ "))?;
@@ -50,8 +255,9 @@ impl Display for Data {
             Data::Box(b) => f.write_fmt(format_args!("box {:p}", b.as_ptr())),
             Data::Block(b) => f.write_fmt(format_args!("block {:p}", b.block.as_ref())),
             Data::Fn(b) => f.write_fmt(format_args!("fn {:p}", b.block.as_ref())),
-            Data::External(e) => f.write_fmt(format_args!("external {:p}",e.as_ref())),
+            Data::External(e) => f.write_fmt(format_args!("external {:p}", e.as_ref())),
             Data::BuiltinFunc(b) => f.write_fmt(format_args!("built-in {:p}", b)),
+            Data::Error(e) => f.write_fmt(format_args!("error {}: {}", e.kind, e.message)),
         }
     }
 }
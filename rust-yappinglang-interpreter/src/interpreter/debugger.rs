@@ -0,0 +1,251 @@
+use crate::interpreter::data::Data;
+use crate::interpreter::{run_tree, BlockExec, Runtime};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::utils::ResultToString;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+#[derive(Default)]
+pub struct Breakpoints {
+    /// Lines by canonical path of their file.
+    pub lines: BTreeMap<PathBuf, BTreeSet<u64>>,
+    pub functions: BTreeSet<String>,
+}
+
+impl Breakpoints {
+    /// Key of the lines in `file`, `None` when it is not a file on disk.
+    pub fn source(file: &str) -> Option<PathBuf> {
+        std::fs::canonicalize(file).ok()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    StepIn,
+    StepOver,
+    StepOut,
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Run,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+pub enum PauseReason {
+    Entry,
+    Step,
+    Breakpoint(u64),
+    Function(String),
+    Interrupt,
+}
+
+/// One `BlockExec` on the call stack: the root block, a `Def-fn` call or a block run by a builtin.
+pub struct Frame {
+    pub name: String,
+    /// File of the block the frame was entered with.
+    pub file: Rc<str>,
+    pub line: u64,
+    pub defs: HashMap<String, Data>,
+    pub captured: HashSet<String>,
+}
+
+impl Frame {
+    /// Definitions made inside the block, without captured vars and builtins.
+    pub fn locals(&self) -> Vec<(&String, &Data)> {
+        let mut locals: Vec<_> = self
+            .defs
+            .iter()
+            .filter(|(k, v)| !self.captured.contains(*k) && !matches!(v, Data::BuiltinFunc(_)))
+            .collect();
+        locals.sort_by(|a, b| a.0.cmp(b.0));
+        locals
+    }
+
+    pub fn captured(&self) -> Vec<(&String, &Data)> {
+        let mut captured: Vec<_> = self
+            .defs
+            .iter()
+            .filter(|(k, _)| self.captured.contains(*k))
+            .collect();
+        captured.sort_by(|a, b| a.0.cmp(b.0));
+        captured
+    }
+}
+
+pub struct Pause<'a> {
+    pub reason: PauseReason,
+    pub line: u64,
+    /// Innermost frame is last.
+    pub frames: &'a [Frame],
+    pub stack: &'a [Data],
+    pub breakpoints: &'a mut Breakpoints,
+    prev_defs: &'a HashMap<String, Data>,
+    runtime: &'a Rc<Runtime>,
+}
+
+impl Pause<'_> {
+    /// Runs `source` on a copy of the data stack with the defs seen by `frame`.
+    /// Output goes where the program prints, defs and stack of the program are not changed
+    /// and the resulting stack is returned.
+    pub fn evaluate(&self, frame: usize, source: &str) -> Result<Vec<Data>, String> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let ast = Parser::new(tokens).build_tree()?;
+        let block = run_tree::load(&ast, &"".into());
+        let frame = &self.frames[frame];
+        let mut exec = BlockExec::new(frame.defs.clone(), self.runtime.clone());
+        let mut stack = self.stack.to_vec();
+        exec.run_block(&mut stack, self.prev_defs, block)
+            .str_res()?;
+        Ok(stack)
+    }
+}
+
+pub trait Frontend {
+    fn paused(&mut self, pause: &mut Pause) -> Result<Resume, String>;
+
+    /// Polled before every expression while running, `true` pauses.
+    fn interrupt(&mut self, _breakpoints: &mut Breakpoints) -> Result<bool, String> {
+        Ok(false)
+    }
+}
+
+pub struct Debugger {
+    pub breakpoints: Breakpoints,
+    frontend: Box<dyn Frontend>,
+    frames: Vec<Frame>,
+    calls: Vec<String>,
+    mode: Mode,
+    entry: bool,
+    fn_break: Option<String>,
+    /// Canonical paths of the files seen in frames.
+    sources: HashMap<Rc<str>, Option<PathBuf>>,
+}
+
+impl Debugger {
+    /// The debugger pauses before the first expression so breakpoints can be set.
+    pub fn new(frontend: Box<dyn Frontend>) -> Self {
+        Self {
+            breakpoints: Breakpoints::default(),
+            frontend,
+            frames: vec![],
+            calls: vec![],
+            mode: Mode::StepIn,
+            entry: true,
+            fn_break: None,
+            sources: HashMap::new(),
+        }
+    }
+
+    pub fn set_stop_on_entry(&mut self, stop: bool) {
+        self.entry = stop;
+        self.mode = if stop { Mode::StepIn } else { Mode::Run };
+    }
+
+    pub(super) fn call(&mut self, name: &str) {
+        self.calls.push(name.to_string());
+    }
+
+    pub(super) fn ret(&mut self) {
+        self.calls.pop();
+    }
+
+    pub(super) fn enter(
+        &mut self,
+        caller_defs: &HashMap<String, Data>,
+        file: Rc<str>,
+        captured: HashSet<String>,
+    ) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.defs = caller_defs.clone();
+        }
+        let name = if self.frames.is_empty() {
+            "main".to_string()
+        } else {
+            self.calls
+                .last()
+                .cloned()
+                .unwrap_or_else(|| "block".to_string())
+        };
+        if self.breakpoints.functions.contains(&name) {
+            self.fn_break = Some(name.clone());
+        }
+        self.frames.push(Frame {
+            name,
+            file,
+            line: 0,
+            defs: HashMap::new(),
+            captured,
+        });
+    }
+
+    pub(super) fn leave(&mut self) {
+        self.frames.pop();
+    }
+
+    pub(super) fn exp(
+        &mut self,
+        line: u64,
+        defs: &HashMap<String, Data>,
+        stack: &[Data],
+        prev_defs: &HashMap<String, Data>,
+        runtime: &Rc<Runtime>,
+    ) -> Result<(), String> {
+        let depth = self.frames.len();
+        let frame = self.frames.last_mut().unwrap();
+        let new_line = frame.line != line;
+        frame.line = line;
+        let on_breakpoint = new_line
+            && !self.breakpoints.lines.is_empty()
+            && self
+                .sources
+                .entry(frame.file.clone())
+                .or_insert_with_key(|file| Breakpoints::source(file))
+                .as_ref()
+                .and_then(|source| self.breakpoints.lines.get(source))
+                .is_some_and(|lines| lines.contains(&line));
+        let interrupt =
+            matches!(self.mode, Mode::Run) && self.frontend.interrupt(&mut self.breakpoints)?;
+        let reason = if let Some(name) = self.fn_break.take() {
+            Some(PauseReason::Function(name))
+        } else if on_breakpoint {
+            Some(PauseReason::Breakpoint(line))
+        } else if interrupt {
+            Some(PauseReason::Interrupt)
+        } else {
+            match self.mode {
+                Mode::StepIn if self.entry => Some(PauseReason::Entry),
+                Mode::StepIn => Some(PauseReason::Step),
+                Mode::StepOver(d) if depth <= d => Some(PauseReason::Step),
+                Mode::StepOut(d) if depth < d => Some(PauseReason::Step),
+                _ => None,
+            }
+        };
+        let Some(reason) = reason else {
+            return Ok(());
+        };
+        self.entry = false;
+        frame.defs = defs.clone();
+        let resume = self.frontend.paused(&mut Pause {
+            reason,
+            line,
+            frames: &self.frames,
+            stack,
+            breakpoints: &mut self.breakpoints,
+            prev_defs,
+            runtime,
+        })?;
+        self.mode = match resume {
+            Resume::Continue => Mode::Run,
+            Resume::StepIn => Mode::StepIn,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+        };
+        Ok(())
+    }
+}
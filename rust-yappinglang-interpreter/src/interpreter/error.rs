@@ -0,0 +1,77 @@
+use crate::interpreter::data::{Data, ErrorValue};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+/// Why a block stopped before its end.
+pub enum Error {
+    /// Value raised by `Throw` or a failing builtin, caught by `Try` and `Catch`.
+    Raised(Data),
+    /// Stops the whole program, not catchable.
+    Abort(String),
+    /// `Break`, ends the innermost loop.
+    Break,
+    /// `Continue`, ends the current run of the innermost loop body.
+    Continue,
+}
+
+impl Error {
+    pub fn new(kind: &str, message: String) -> Self {
+        Error::Raised(Data::Error(ErrorValue {
+            kind: kind.to_string(),
+            message,
+            location: None,
+        }))
+    }
+
+    /// Records where a raised error came from, the innermost location wins.
+    pub fn locate(self, file: &Rc<str>, line: u64) -> Self {
+        match self {
+            Error::Raised(Data::Error(mut e)) if e.location.is_none() => {
+                e.location = Some((file.clone(), line));
+                Error::Raised(Data::Error(e))
+            }
+            e => e,
+        }
+    }
+
+    /// `Break` and `Continue` stop at function calls and the root, outside a loop they raise.
+    pub fn outside_loop(self) -> Self {
+        match self {
+            Error::Break | Error::Continue => Error::new("error", format!("{self} outside a loop")),
+            e => e,
+        }
+    }
+}
+
+/// Errors from scanner, parser and io have kind `error`.
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::new("error", message)
+    }
+}
+
+impl std::fmt::Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Raised(Data::Error(e)) => match &e.location {
+                Some((file, line)) if !file.is_empty() => {
+                    f.write_fmt(format_args!("{file}:{line}: {}", e.message))
+                }
+                Some((_, line)) => f.write_fmt(format_args!("line {line}: {}", e.message)),
+                None => f.write_str(&e.message),
+            },
+            Error::Raised(data) => f.write_fmt(format_args!("uncaught {data}")),
+            Error::Abort(message) => f.write_str(message),
+            Error::Break => f.write_str("Break"),
+            Error::Continue => f.write_str("Continue"),
+        }
+    }
+}
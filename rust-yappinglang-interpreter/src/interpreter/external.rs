@@ -1,14 +1,12 @@
-use std::cell::{Cell, RefCell, RefMut, UnsafeCell};
-use std::io::BufRead;
-use std::ops::{Add, AddAssign};
-use std::rc::Rc;
 use crate::interpreter::data::Data;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 pub trait External {
-    fn apply(&mut self, func: String, stack: &mut Vec<Data>) -> Result<(),String>;
+    fn apply(&mut self, func: String, stack: &mut Vec<Data>) -> Result<(), String>;
     fn name(&mut self) -> String;
 }
 
-pub fn new_external(name: String) -> Result<Rc<RefCell<dyn External>>,String> {
+pub fn new_external(_name: String) -> Result<Rc<RefCell<dyn External>>, String> {
     todo!()
 }
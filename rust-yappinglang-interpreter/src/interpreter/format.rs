@@ -0,0 +1,252 @@
+use crate::bigint::BigInt;
+use crate::interpreter::data::Data;
+
+/// Template of `Format`: text with `{index:spec}` placeholders like Rust's `format!`,
+/// `{{` and `}}` are literal braces.
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+enum Piece {
+    Text(String),
+    Value(usize, Spec),
+}
+
+/// Largest width or precision of a placeholder.
+const MAX_SPEC_NUMBER: usize = 65535;
+
+/// `[[fill]align][+][#][0][width][.precision][x|X|b|o]`
+struct Spec {
+    fill: char,
+    align: Option<char>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    radix: Option<char>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut pieces = vec![];
+        let mut text = String::new();
+        let mut next_index = 0;
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err("unmatched '}' in template, write '}}' for a brace".to_string()),
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(format!("unclosed '{{{placeholder}' in template")),
+                        }
+                    }
+                    let (index, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+                    let index = if index.is_empty() {
+                        next_index += 1;
+                        next_index - 1
+                    } else {
+                        index
+                            .parse()
+                            .map_err(|_| format!("bad placeholder '{{{placeholder}}}'"))?
+                    };
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    pieces.push(Piece::Value(index, Spec::parse(spec)?));
+                }
+                c => text.push(c),
+            }
+        }
+        pieces.push(Piece::Text(text));
+        let template = Template { pieces };
+        for index in 0..template.value_count() {
+            let used = template
+                .pieces
+                .iter()
+                .any(|piece| matches!(piece, Piece::Value(i, _) if *i == index));
+            if !used {
+                return Err(format!("value {index} is not used in the template"));
+            }
+        }
+        Ok(template)
+    }
+
+    /// Number of values taken from the stack, the first popped is `{0}`.
+    pub fn value_count(&self) -> usize {
+        self.pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Value(index, _) => Some(index + 1),
+                Piece::Text(_) => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn render(&self, values: &[Data]) -> Result<String, String> {
+        let mut out = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Value(index, spec) => out.push_str(&spec.render(&values[*index])?),
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Spec, String> {
+        let bad = || format!("bad format spec '{spec}'");
+        let chars: Vec<char> = spec.chars().collect();
+        let mut parsed = Spec {
+            fill: ' ',
+            align: None,
+            plus: false,
+            alternate: false,
+            zero: false,
+            width: 0,
+            precision: None,
+            radix: None,
+        };
+        let mut current = 0;
+        if chars.len() >= 2 && "<^>".contains(chars[1]) {
+            parsed.fill = chars[0];
+            parsed.align = Some(chars[1]);
+            current = 2;
+        } else if chars.first().is_some_and(|c| "<^>".contains(*c)) {
+            parsed.align = Some(chars[0]);
+            current = 1;
+        }
+        let mut flag = |flag: char| {
+            let found = chars.get(current) == Some(&flag);
+            current += found as usize;
+            found
+        };
+        parsed.plus = flag('+');
+        parsed.alternate = flag('#');
+        parsed.zero = flag('0');
+        let number = |current: &mut usize| {
+            let digits: String = chars[*current..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            *current += digits.len();
+            match digits.parse::<usize>() {
+                Ok(number) if number <= MAX_SPEC_NUMBER => Ok(Some(number)),
+                _ if digits.is_empty() => Ok(None),
+                _ => Err(format!(
+                    "{digits} in format spec '{spec}' is over the maximum of {MAX_SPEC_NUMBER}"
+                )),
+            }
+        };
+        parsed.width = number(&mut current)?.unwrap_or(0);
+        if chars.get(current) == Some(&'.') {
+            current += 1;
+            parsed.precision = Some(number(&mut current)?.ok_or_else(bad)?);
+        }
+        if let Some(radix) = chars.get(current).filter(|c| "xXbo".contains(**c)) {
+            parsed.radix = Some(*radix);
+            current += 1;
+        }
+        if current != chars.len() {
+            return Err(bad());
+        }
+        Ok(parsed)
+    }
+
+    fn render(&self, value: &Data) -> Result<String, String> {
+        let (text, numeric) = match self.radix {
+            Some(radix) => (self.radix_text(radix, value)?, true),
+            None => self.text(value),
+        };
+        let (sign, body) = match text.strip_prefix('-').filter(|_| numeric) {
+            Some(body) => ("-", body),
+            None if numeric && self.plus => ("+", text.as_str()),
+            None => ("", text.as_str()),
+        };
+        let prefix = match (self.alternate, self.radix) {
+            (true, Some('x' | 'X')) => "0x",
+            (true, Some('b')) => "0b",
+            (true, Some('o')) => "0o",
+            _ => "",
+        };
+        let len = sign.chars().count() + prefix.len() + body.chars().count();
+        let padding = self.width.saturating_sub(len);
+        if self.zero && numeric {
+            return Ok(format!("{sign}{prefix}{}{body}", "0".repeat(padding)));
+        }
+        let default_align = if numeric { '>' } else { '<' };
+        let (before, after) = match self.align.unwrap_or(default_align) {
+            '<' => (0, padding),
+            '^' => (padding / 2, padding - padding / 2),
+            _ => (padding, 0),
+        };
+        let fill = |count| self.fill.to_string().repeat(count);
+        Ok(format!(
+            "{}{sign}{prefix}{body}{}",
+            fill(before),
+            fill(after)
+        ))
+    }
+
+    /// Strings without quotes, numbers without suffixes, precision is places or the String length.
+    fn text(&self, value: &Data) -> (String, bool) {
+        match (value, self.precision) {
+            (Data::String(str), None) => (str.clone(), false),
+            (Data::String(str), Some(len)) => (str.chars().take(len).collect(), false),
+            (Data::Decimal(dec), None) => (dec.to_string(), true),
+            (Data::Decimal(dec), Some(places)) => (format!("{dec:.places$}"), true),
+            (Data::Integer(_) | Data::BigInteger(_) | Data::Rational(_), Some(places)) => {
+                let rat = value
+                    .as_rational()
+                    .unwrap_or_else(|| BigInt::from(0).into());
+                (rat.to_fixed(places as u32), true)
+            }
+            (Data::Rational(rat), None) => {
+                (rat.to_string().trim_end_matches('r').to_string(), true)
+            }
+            (Data::Integer(_) | Data::BigInteger(_), None) => (value.to_string(), true),
+            _ => (value.to_string(), false),
+        }
+    }
+
+    fn radix_text(&self, radix: char, value: &Data) -> Result<String, String> {
+        let Some(int) = value.as_big_int() else {
+            return Err(format!("expect Integer for '{radix}', found {value}"));
+        };
+        let base = BigInt::from(match radix {
+            'b' => 2,
+            'o' => 8,
+            _ => 16,
+        });
+        let mut digits = vec![];
+        let mut rest = int.abs();
+        while let Some((quotient, remainder)) = rest.div_rem(&base).filter(|_| !rest.is_zero()) {
+            let digit = remainder.to_i64().unwrap_or_default() as u32;
+            digits.push(char::from_digit(digit, 16).unwrap_or('0'));
+            rest = quotient;
+        }
+        if digits.is_empty() {
+            digits.push('0');
+        }
+        let sign = if int.is_negative() { "-" } else { "" };
+        let text: String = digits.iter().rev().collect();
+        if radix == 'X' {
+            Ok(format!("{sign}{}", text.to_uppercase()))
+        } else {
+            Ok(format!("{sign}{text}"))
+        }
+    }
+}
@@ -0,0 +1,450 @@
+use crate::bigint::BigInt;
+use crate::interpreter::data::{self, Data, ErrorValue};
+use crate::interpreter::run_tree::{Block, Exp, ExpData};
+use crate::interpreter::{builtins, external, StackFn};
+use crate::rational::Rational;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const MAGIC: &[u8] = b"YAPIMG";
+const VERSION: u32 = 1;
+
+/// Definitions and stack in the binary image format.
+///
+/// Code blocks and boxes are written once and referenced by index after,
+/// so sharing and cycles survive a round trip. Builtins and externals are
+/// stored by name.
+pub fn save(defs: &HashMap<String, Data>, stack: &[Data]) -> Result<Vec<u8>, String> {
+    let mut writer = Writer {
+        out: MAGIC.to_vec(),
+        blocks: HashMap::new(),
+        boxes: HashMap::new(),
+        builtins: builtins()
+            .into_iter()
+            .map(|(name, func)| (func as usize, name))
+            .collect(),
+    };
+    writer.u32(VERSION);
+    let mut names: Vec<_> = defs.keys().collect();
+    names.sort();
+    writer.len(names.len());
+    for name in names {
+        writer.str(name);
+        writer.data(&defs[name])?;
+    }
+    writer.len(stack.len());
+    for data in stack {
+        writer.data(data)?;
+    }
+    Ok(writer.out)
+}
+
+/// Reads what `save` wrote.
+pub fn load(bytes: &[u8]) -> Result<(HashMap<String, Data>, Vec<Data>), String> {
+    let Some(bytes) = bytes.strip_prefix(MAGIC) else {
+        return Err("not an image file".to_string());
+    };
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        blocks: vec![],
+        boxes: vec![],
+        builtins: builtins(),
+    };
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(format!("image version {version} is not supported"));
+    }
+    let mut defs = HashMap::new();
+    for _ in 0..reader.len()? {
+        let name = reader.str()?;
+        defs.insert(name, reader.data()?);
+    }
+    let mut stack = vec![];
+    for _ in 0..reader.len()? {
+        stack.push(reader.data()?);
+    }
+    Ok((defs, stack))
+}
+
+fn builtins() -> HashMap<String, StackFn> {
+    let mut all = HashMap::new();
+    for group in ["base", "math"] {
+        for (name, data) in builtins::group(group).unwrap_or_default() {
+            if let Data::BuiltinFunc(func) = data {
+                all.insert(name, func);
+            }
+        }
+    }
+    all
+}
+
+struct Writer {
+    out: Vec<u8>,
+    blocks: HashMap<*const Block, usize>,
+    boxes: HashMap<*const Cell<Data>, usize>,
+    builtins: HashMap<usize, String>,
+}
+
+impl Writer {
+    fn u32(&mut self, n: u32) {
+        self.out.extend(n.to_le_bytes());
+    }
+
+    fn u64(&mut self, n: u64) {
+        self.out.extend(n.to_le_bytes());
+    }
+
+    fn len(&mut self, n: usize) {
+        self.u64(n as u64);
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.out.extend(s.as_bytes());
+    }
+
+    fn rational(&mut self, rat: &Rational) {
+        self.str(&format!("{}/{}", rat.numerator(), rat.denominator()));
+    }
+
+    fn data(&mut self, data: &Data) -> Result<(), String> {
+        match data {
+            Data::String(s) => {
+                self.out.push(0);
+                self.str(s);
+            }
+            Data::Integer(int) => {
+                self.out.push(1);
+                self.u64(*int as u64);
+            }
+            Data::Decimal(dec) => {
+                self.out.push(2);
+                self.u64(dec.to_bits());
+            }
+            Data::List(list) => {
+                self.out.push(3);
+                self.len(list.len());
+                for d in list {
+                    self.data(d)?;
+                }
+            }
+            Data::Dict(dict) => {
+                self.out.push(4);
+                self.len(dict.len());
+                for (k, v) in dict {
+                    self.data(k)?;
+                    self.data(v)?;
+                }
+            }
+            Data::Box(cell) => {
+                self.out.push(5);
+                let ptr = Rc::as_ptr(cell);
+                if let Some(id) = self.boxes.get(&ptr) {
+                    self.len(*id);
+                } else {
+                    let id = self.boxes.len();
+                    self.boxes.insert(ptr, id);
+                    self.len(id);
+                    let inner = cell.replace(Data::Integer(0));
+                    let res = self.data(&inner);
+                    cell.set(inner);
+                    res?;
+                }
+            }
+            Data::Block(block) => {
+                self.out.push(6);
+                self.block(block)?;
+            }
+            Data::Fn(block) => {
+                self.out.push(7);
+                self.block(block)?;
+            }
+            Data::External(ext) => {
+                self.out.push(8);
+                let name = ext.borrow_mut().name();
+                self.str(&name);
+            }
+            Data::BuiltinFunc(func) => {
+                self.out.push(9);
+                let Some(name) = self.builtins.get(&(*func as usize)).cloned() else {
+                    return Err("built-in without a name can not be saved".to_string());
+                };
+                self.str(&name);
+            }
+            Data::BigInteger(int) => {
+                self.out.push(11);
+                self.str(&int.to_string());
+            }
+            Data::Rational(rat) => {
+                self.out.push(12);
+                self.rational(rat);
+            }
+            Data::Error(e) => {
+                self.out.push(10);
+                self.str(&e.kind);
+                self.str(&e.message);
+                match &e.location {
+                    Some((file, line)) => {
+                        self.out.push(1);
+                        self.str(file);
+                        self.u64(*line);
+                    }
+                    None => self.out.push(0),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &data::Block) -> Result<(), String> {
+        self.code(&block.block);
+        let mut names: Vec<_> = block.captured_vars.keys().collect();
+        names.sort();
+        self.len(names.len());
+        for name in names {
+            self.str(name);
+            self.data(&block.captured_vars[name])?;
+        }
+        Ok(())
+    }
+
+    /// Index of `code`, followed by the code itself the first time.
+    fn code(&mut self, code: &Rc<Block>) {
+        let ptr = Rc::as_ptr(code);
+        if let Some(id) = self.blocks.get(&ptr) {
+            self.len(*id);
+            return;
+        }
+        let id = self.blocks.len();
+        self.blocks.insert(ptr, id);
+        self.len(id);
+        self.str(&code.file);
+        self.out.push(code.module as u8);
+        let mut vars: Vec<_> = code.capture_vars.iter().collect();
+        vars.sort();
+        self.len(vars.len());
+        for var in vars {
+            self.str(var);
+        }
+        let mut exps = vec![];
+        let mut exp = code.exp.as_deref();
+        while let Some(e) = exp {
+            exps.push(e);
+            exp = e.next_exp.as_deref();
+        }
+        self.len(exps.len());
+        for exp in exps {
+            self.u64(exp.line);
+            match &exp.data {
+                ExpData::Var(var) => {
+                    self.out.push(0);
+                    self.str(var);
+                }
+                ExpData::Block(block) => {
+                    self.out.push(1);
+                    self.code(block);
+                }
+                ExpData::Integer(int) => {
+                    self.out.push(2);
+                    self.u64(*int as u64);
+                }
+                ExpData::Decimal(dec) => {
+                    self.out.push(3);
+                    self.u64(dec.to_bits());
+                }
+                ExpData::String(s) => {
+                    self.out.push(4);
+                    self.str(s);
+                }
+                ExpData::BigInteger(int) => {
+                    self.out.push(5);
+                    self.str(&int.to_string());
+                }
+                ExpData::Rational(rat) => {
+                    self.out.push(6);
+                    self.rational(rat);
+                }
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// `None` while the block is being read.
+    blocks: Vec<Option<Rc<Block>>>,
+    boxes: Vec<Rc<Cell<Data>>>,
+    builtins: HashMap<String, StackFn>,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        let end = self.pos.checked_add(n).ok_or("image file is truncated")?;
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or("image file is truncated")?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().unwrap_or_default(),
+        ))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(
+            self.take(8)?.try_into().unwrap_or_default(),
+        ))
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        Ok(self.u64()? as usize)
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|e| e.to_string())
+    }
+
+    fn big_int(&mut self) -> Result<BigInt, String> {
+        let digits = self.str()?;
+        BigInt::parse(&digits).ok_or_else(|| format!("bad integer {digits}"))
+    }
+
+    fn rational(&mut self) -> Result<Rational, String> {
+        let fraction = self.str()?;
+        Rational::parse(&fraction).ok_or_else(|| format!("bad rational {fraction}"))
+    }
+
+    fn data(&mut self) -> Result<Data, String> {
+        Ok(match self.u8()? {
+            0 => Data::String(self.str()?),
+            1 => Data::Integer(self.u64()? as i64),
+            2 => Data::Decimal(f64::from_bits(self.u64()?)),
+            3 => {
+                let mut list = vec![];
+                for _ in 0..self.len()? {
+                    list.push(self.data()?);
+                }
+                Data::List(list)
+            }
+            4 => {
+                if self.len()? != 0 {
+                    return Err("Dict entries can not be loaded".to_string());
+                }
+                Data::Dict(HashMap::new())
+            }
+            5 => {
+                let id = self.len()?;
+                if let Some(cell) = self.boxes.get(id) {
+                    Data::Box(cell.clone())
+                } else {
+                    let cell = Rc::new(Cell::new(Data::Integer(0)));
+                    self.boxes.push(cell.clone());
+                    cell.set(self.data()?);
+                    Data::Box(cell)
+                }
+            }
+            6 => Data::Block(self.block()?),
+            7 => Data::Fn(self.block()?),
+            8 => Data::External(external::new_external(self.str()?)?),
+            9 => {
+                let name = self.str()?;
+                let func = self
+                    .builtins
+                    .get(&name)
+                    .ok_or_else(|| format!("unknown built-in '{name}'"))?;
+                Data::BuiltinFunc(*func)
+            }
+            10 => {
+                let kind = self.str()?;
+                let message = self.str()?;
+                let location = match self.u8()? {
+                    0 => None,
+                    _ => Some((self.str()?.into(), self.u64()?)),
+                };
+                Data::Error(ErrorValue {
+                    kind,
+                    message,
+                    location,
+                })
+            }
+            11 => Data::BigInteger(Rc::new(self.big_int()?)),
+            12 => Data::Rational(Rc::new(self.rational()?)),
+            tag => return Err(format!("bad data tag {tag}")),
+        })
+    }
+
+    fn block(&mut self) -> Result<data::Block, String> {
+        let block = self.code()?;
+        let mut captured_vars = HashMap::new();
+        for _ in 0..self.len()? {
+            let name = self.str()?;
+            captured_vars.insert(name, self.data()?);
+        }
+        Ok(data::Block {
+            block,
+            captured_vars,
+        })
+    }
+
+    fn code(&mut self) -> Result<Rc<Block>, String> {
+        let id = self.len()?;
+        if let Some(block) = self.blocks.get(id) {
+            return block
+                .clone()
+                .ok_or_else(|| format!("block {id} contains itself"));
+        }
+        if id != self.blocks.len() {
+            return Err(format!("bad block index {id}"));
+        }
+        self.blocks.push(None);
+        let file = self.str()?.into();
+        let module = self.u8()? != 0;
+        let mut capture_vars = std::collections::HashSet::new();
+        for _ in 0..self.len()? {
+            capture_vars.insert(self.str()?);
+        }
+        let mut exps = vec![];
+        for _ in 0..self.len()? {
+            let line = self.u64()?;
+            let data = match self.u8()? {
+                0 => ExpData::Var(self.str()?),
+                1 => ExpData::Block(self.code()?),
+                2 => ExpData::Integer(self.u64()? as i64),
+                3 => ExpData::Decimal(f64::from_bits(self.u64()?)),
+                4 => ExpData::String(self.str()?),
+                5 => ExpData::BigInteger(Rc::new(self.big_int()?)),
+                6 => ExpData::Rational(Rc::new(self.rational()?)),
+                tag => return Err(format!("bad expression tag {tag}")),
+            };
+            exps.push((data, line));
+        }
+        let mut exp = None;
+        for (data, line) in exps.into_iter().rev() {
+            exp = Some(Box::new(Exp {
+                data,
+                line,
+                next_exp: exp,
+            }));
+        }
+        let block = Rc::new(Block {
+            exp,
+            capture_vars,
+            file,
+            module,
+        });
+        self.blocks[id] = Some(block.clone());
+        Ok(block)
+    }
+}
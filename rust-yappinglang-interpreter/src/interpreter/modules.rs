@@ -0,0 +1,187 @@
+use crate::interpreter::data::{self, Data};
+use crate::interpreter::error::Error;
+use crate::interpreter::run_tree::{self, Block};
+use crate::interpreter::{builtins, BlockExec, Runtime};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// Files loaded by `Use-file`, parsed once per canonical path.
+#[derive(Default)]
+pub struct Modules {
+    cache: HashMap<PathBuf, Rc<Block>>,
+    /// Files whose top-level block is running, outermost first.
+    running: Vec<Rc<str>>,
+    /// Directory and entry file of each package.
+    pub packages: HashMap<String, (PathBuf, PathBuf)>,
+    /// Searched after the directory of the including file and packages.
+    pub library_path: Vec<PathBuf>,
+}
+
+impl Modules {
+    /// Library path from the `YAP_PATH` environment variable.
+    pub fn from_env() -> Self {
+        Self {
+            library_path: std::env::var_os("YAP_PATH")
+                .map(|paths| std::env::split_paths(&paths).collect())
+                .unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    /// Finds `name` next to `including`, then in packages, then in the library path.
+    /// Code without a file looks in the working directory first.
+    fn resolve(&self, name: &str, including: &str) -> Result<PathBuf, Error> {
+        let name = Path::new(name);
+        let mut candidates = vec![];
+        if name.is_absolute() {
+            candidates.push(name.to_path_buf());
+        } else {
+            match Path::new(including).parent() {
+                Some(dir) if !including.is_empty() => candidates.push(dir.join(name)),
+                _ => candidates.push(name.to_path_buf()),
+            }
+            let mut components = name.components();
+            if let Some(Component::Normal(first)) = components.next() {
+                if let Some((dir, entry)) = self.packages.get(&*first.to_string_lossy()) {
+                    let rest = components.as_path();
+                    if rest.as_os_str().is_empty() {
+                        candidates.push(entry.clone());
+                    } else {
+                        candidates.push(dir.join(rest));
+                    }
+                }
+            }
+            candidates.extend(self.library_path.iter().map(|dir| dir.join(name)));
+        }
+        for path in &candidates {
+            if path.is_file() {
+                return std::fs::canonicalize(path)
+                    .map_err(|e| Error::new("import", format!("{}: {e}", path.display())));
+            }
+        }
+        let searched: Vec<_> = candidates.iter().map(|p| p.display().to_string()).collect();
+        Err(Error::new(
+            "import",
+            format!(
+                "file '{}' not found, searched {}",
+                name.display(),
+                searched.join(", ")
+            ),
+        ))
+    }
+
+    fn load(&mut self, path: PathBuf) -> Result<Rc<Block>, Error> {
+        if let Some(block) = self.cache.get(&path) {
+            return Ok(block.clone());
+        }
+        let file: Rc<str> = path.to_string_lossy().into();
+        let in_file = |e: String| Error::new("import", format!("{file}: {e}"));
+        let source = std::fs::read_to_string(&path).map_err(|e| in_file(e.to_string()))?;
+        let tokens = Scanner::new(&source).scan_tokens().map_err(in_file)?;
+        let ast = Parser::new(tokens).build_tree().map_err(in_file)?;
+        let block = run_tree::load(&ast, &file);
+        self.cache.insert(path, block.clone());
+        Ok(block)
+    }
+}
+
+impl Runtime {
+    /// Fails when `file` is already running, which would never end.
+    pub(super) fn enter_module(&self, file: &Rc<str>) -> Result<(), Error> {
+        let mut modules = self.modules.borrow_mut();
+        if !file.is_empty() && modules.running.contains(file) {
+            let start = modules.running.iter().position(|f| f == file).unwrap_or(0);
+            let mut chain: Vec<&str> = modules.running[start..].iter().map(|f| &**f).collect();
+            chain.push(file);
+            return Err(Error::new(
+                "import",
+                format!("import cycle: {}", chain.join(" -> ")),
+            ));
+        }
+        modules.running.push(file.clone());
+        Ok(())
+    }
+
+    pub(super) fn leave_module(&self) {
+        self.modules.borrow_mut().running.pop();
+    }
+}
+
+/// Block of file `name`, resolved from the file of the running block.
+pub fn use_file(
+    block_exec: &mut BlockExec,
+    prev_defs: &HashMap<String, Data>,
+    name: &str,
+) -> Result<data::Block, Error> {
+    let block = {
+        let mut modules = block_exec.runtime.modules.borrow_mut();
+        let path = modules.resolve(name, &block_exec.file)?;
+        modules.load(path)?
+    };
+    let captured_vars = block_exec.capture(&block.capture_vars, prev_defs)?;
+    Ok(data::Block {
+        block,
+        captured_vars,
+    })
+}
+
+/// Exported definitions of builtin group or file `name`.
+/// The file runs on its own stack, its functions keep seeing the other definitions of the file.
+pub fn import(
+    block_exec: &mut BlockExec,
+    prev_defs: &HashMap<String, Data>,
+    name: &str,
+) -> Result<HashMap<String, Data>, Error> {
+    if let Some(defs) = builtins::group(name) {
+        return Ok(defs);
+    }
+    let block = use_file(block_exec, prev_defs, name)?;
+    let mut defs = prev_defs.clone();
+    for (k, v) in &block_exec.defs {
+        defs.insert(k.clone(), v.clone());
+    }
+    let mut module = BlockExec::new(block.captured_vars, block_exec.runtime.clone());
+    block_exec.runtime.enter(&block_exec.defs, &block.block);
+    let res = module.run_block(&mut vec![], &defs, block.block.clone());
+    block_exec.runtime.leave();
+    res.map_err(Error::outside_loop)?;
+    let names = module
+        .exports
+        .take()
+        .unwrap_or_else(|| module.defs.keys().cloned().collect::<HashSet<_>>());
+    let mut exports = HashMap::new();
+    for name in names {
+        let Some(data) = module.defs.get(&name) else {
+            return Err(Error::new(
+                "import",
+                format!(
+                    "'{name}' is exported but not defined in {}",
+                    block.block.file
+                ),
+            ));
+        };
+        exports.insert(name, with_scope(data.clone(), &module.defs));
+    }
+    Ok(exports)
+}
+
+/// Adds `defs` to what a block captured, so it still finds them under a prefix.
+fn with_scope(data: Data, defs: &HashMap<String, Data>) -> Data {
+    let scoped = |mut block: data::Block| {
+        for (k, v) in defs {
+            block
+                .captured_vars
+                .entry(k.clone())
+                .or_insert_with(|| v.clone());
+        }
+        block
+    };
+    match data {
+        Data::Fn(block) => Data::Fn(scoped(block)),
+        Data::Block(block) => Data::Block(scoped(block)),
+        data => data,
+    }
+}
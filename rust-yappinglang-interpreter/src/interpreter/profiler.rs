@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+pub struct Stat {
+    pub calls: u64,
+    /// Time from call to return, counted once for recursive calls.
+    pub inclusive: Duration,
+    /// Time not spent in nested calls.
+    pub exclusive: Duration,
+    pub builtin: bool,
+}
+
+struct Call {
+    name: String,
+    start: Instant,
+    children: Duration,
+}
+
+/// Records timings of `Def-fn` and builtin calls.
+pub struct Profiler {
+    calls: Vec<Call>,
+    stats: HashMap<String, Stat>,
+    /// Exclusive time by call path `main;Fn;Builtin`.
+    folded: HashMap<String, Duration>,
+    peak_depth: usize,
+    peak_stack: usize,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let mut profiler = Self {
+            calls: vec![],
+            stats: HashMap::new(),
+            folded: HashMap::new(),
+            peak_depth: 0,
+            peak_stack: 0,
+        };
+        profiler.call("main", false, 0);
+        profiler
+    }
+
+    pub(super) fn call(&mut self, name: &str, builtin: bool, stack_len: usize) {
+        self.calls.push(Call {
+            name: name.to_string(),
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+        self.stats.entry(name.to_string()).or_default().builtin = builtin;
+        self.peak_depth = self.peak_depth.max(self.calls.len() - 1);
+        self.peak_stack = self.peak_stack.max(stack_len);
+    }
+
+    pub(super) fn ret(&mut self) {
+        let Some(call) = self.calls.pop() else {
+            return;
+        };
+        let elapsed = call.start.elapsed();
+        let exclusive = elapsed.saturating_sub(call.children);
+        if let Some(parent) = self.calls.last_mut() {
+            parent.children += elapsed;
+        }
+        let recursive = self.calls.iter().any(|c| c.name == call.name);
+        let stat = self.stats.entry(call.name.clone()).or_default();
+        stat.calls += 1;
+        stat.exclusive += exclusive;
+        if !recursive {
+            stat.inclusive += elapsed;
+        }
+        let mut path = String::new();
+        for c in &self.calls {
+            path.push_str(&c.name);
+            path.push(';');
+        }
+        path.push_str(&call.name);
+        *self.folded.entry(path).or_default() += exclusive;
+    }
+
+    /// Closes the `main` call, after this the profile is complete.
+    pub fn finish(&mut self) {
+        while !self.calls.is_empty() {
+            self.ret();
+        }
+    }
+
+    /// Table of calls sorted by exclusive time.
+    pub fn report(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        let mut stats: Vec<_> = self.stats.iter().collect();
+        stats.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+        writeln!(
+            out,
+            "{:>10} {:>14} {:>14}  name",
+            "calls", "inclusive ms", "exclusive ms"
+        )?;
+        for (name, stat) in stats {
+            writeln!(
+                out,
+                "{:>10} {:>14.3} {:>14.3}  {}{}",
+                stat.calls,
+                stat.inclusive.as_secs_f64() * 1000.0,
+                stat.exclusive.as_secs_f64() * 1000.0,
+                name,
+                if stat.builtin { " (built-in)" } else { "" }
+            )?;
+        }
+        writeln!(out, "peak call depth: {}", self.peak_depth)?;
+        writeln!(out, "peak data stack: {}", self.peak_stack)
+    }
+
+    /// One `path microseconds` line per call path, as read by flamegraph tools.
+    pub fn write_folded(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        let mut folded: Vec<_> = self.folded.iter().collect();
+        folded.sort();
+        for (path, time) in folded {
+            writeln!(out, "{} {}", path, time.as_micros())?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use crate::interpreter::{BlockExec, Interpreter};
+use crate::bigint::BigInt;
+use crate::rational::Rational;
 use std::collections::HashSet;
 use std::rc::Rc;
 
@@ -6,38 +7,47 @@ use std::rc::Rc;
 pub struct Block {
     pub exp: Option<Box<Exp>>,
     pub capture_vars: HashSet<String>,
+    /// Source file, empty for code without one.
+    pub file: Rc<str>,
+    /// Top-level block of `file`.
+    pub module: bool,
 }
 #[derive(Debug)]
 pub enum ExpData {
     Var(String),
     Block(Rc<Block>),
     Integer(i64),
+    BigInteger(Rc<BigInt>),
+    Rational(Rc<Rational>),
     Decimal(f64),
     String(String),
 }
 #[derive(Debug)]
 pub struct Exp {
     pub data: ExpData,
+    pub line: u64,
     pub next_exp: Option<Box<Exp>>,
 }
 
-pub fn load(block: &crate::ast::Block) -> Rc<Block> {
+pub fn load(block: &crate::ast::Block, file: &Rc<str>) -> Rc<Block> {
+    load_block(block, file, true)
+}
+
+fn load_block(block: &crate::ast::Block, file: &Rc<str>, module: bool) -> Rc<Block> {
     let mut capture_vars = HashSet::new();
-    if let Some(exp) = &block.exp {
-        let exp = load_exp(exp, &mut capture_vars);
-        return Rc::new(Block {
-            exp: Some(Box::new(exp)),
-            capture_vars,
-        });
-    } else {
-        return Rc::new(Block {
-            exp: None,
-            capture_vars,
-        });
-    }
+    let exp = block
+        .exp
+        .as_ref()
+        .map(|exp| Box::new(load_exp(exp, file, &mut capture_vars)));
+    Rc::new(Block {
+        exp,
+        capture_vars,
+        file: file.clone(),
+        module,
+    })
 }
 
-fn load_exp(exp: &crate::ast::Exp, captured_vars: &mut HashSet<String>) -> Exp {
+fn load_exp(exp: &crate::ast::Exp, file: &Rc<str>, captured_vars: &mut HashSet<String>) -> Exp {
     Exp {
         data: match &exp.data {
             crate::ast::ExpData::Var(var) => ExpData::Var(var.clone()),
@@ -46,17 +56,19 @@ fn load_exp(exp: &crate::ast::Exp, captured_vars: &mut HashSet<String>) -> Exp {
                 ExpData::Var(var.clone())
             }
             crate::ast::ExpData::Block(block) => {
-                let block = load(block);
+                let block = load_block(block, file, false);
                 ExpData::Block(block)
             }
             crate::ast::ExpData::Integer(int) => ExpData::Integer(*int),
+            crate::ast::ExpData::BigInteger(int) => ExpData::BigInteger(Rc::new(int.clone())),
+            crate::ast::ExpData::Rational(rat) => ExpData::Rational(Rc::new(rat.clone())),
             crate::ast::ExpData::Decimal(dec) => ExpData::Decimal(*dec),
             crate::ast::ExpData::String(str) => ExpData::String(str.clone()),
         },
-        next_exp: if let Some(next_exp) = &exp.next_exp {
-            Some(Box::new(load_exp(next_exp, captured_vars)))
-        } else {
-            None
-        },
+        line: exp.line,
+        next_exp: exp
+            .next_exp
+            .as_ref()
+            .map(|next_exp| Box::new(load_exp(next_exp, file, captured_vars))),
     }
 }
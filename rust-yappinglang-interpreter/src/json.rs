@@ -0,0 +1,333 @@
+use crate::bigint::BigInt;
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// Number without fraction or exponent, kept exact.
+    Integer(BigInt),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        if let Json::Object(fields) = self {
+            fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if let Json::String(str) = self {
+            Some(str)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Integer(int) => int.to_i64(),
+            Json::Number(num) => Some(*num as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        if let Json::Bool(bool) = self {
+            Some(*bool)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        if let Json::Array(array) = self {
+            Some(array)
+        } else {
+            None
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Json, String> {
+        let mut parser = JsonParser {
+            source: source.chars().collect(),
+            current: 0,
+        };
+        let json = parser.value()?;
+        parser.skip_whitespace();
+        if parser.current < parser.source.len() {
+            return parser.error("trailing characters");
+        }
+        Ok(json)
+    }
+}
+
+/// Builds `Json::Object` from `key => value` pairs, values go through `Json::from`.
+macro_rules! json_object {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        crate::json::Json::Object(vec![$(($key.to_string(), crate::json::Json::from($value))),*])
+    };
+}
+pub(crate) use json_object;
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+impl From<i64> for Json {
+    fn from(value: i64) -> Self {
+        Json::Integer(BigInt::from(value))
+    }
+}
+impl From<u64> for Json {
+    fn from(value: u64) -> Self {
+        Json::Integer(BigInt::parse(&value.to_string()).unwrap_or_else(|| BigInt::from(0)))
+    }
+}
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::from(value as u64)
+    }
+}
+impl From<BigInt> for Json {
+    fn from(value: BigInt) -> Self {
+        Json::Integer(value)
+    }
+}
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Self {
+        Json::Array(value)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(bool) => f.write_fmt(format_args!("{bool}")),
+            Json::Integer(int) => f.write_fmt(format_args!("{int}")),
+            Json::Number(num) => {
+                if num.is_finite() {
+                    f.write_fmt(format_args!("{num}"))
+                } else {
+                    f.write_str("null")
+                }
+            }
+            Json::String(str) => write_string(f, str),
+            Json::Array(array) => {
+                f.write_char('[')?;
+                for (i, json) in array.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    f.write_fmt(format_args!("{json}"))?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, json)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    f.write_fmt(format_args!(":{json}"))?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter<'_>, str: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in str.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => f.write_fmt(format_args!("\\u{:04x}", c as u32))?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct JsonParser {
+    source: Vec<char>,
+    current: usize,
+}
+
+impl JsonParser {
+    fn error<T>(&self, err: &str) -> Result<T, String> {
+        Err(format!("[json char {}] Error: {}", self.current, err))
+    }
+
+    fn peek(&self) -> char {
+        self.source.get(self.current).copied().unwrap_or('\0')
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_whitespace() {
+            self.current += 1;
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), String> {
+        for c in word.chars() {
+            if self.peek() != c {
+                return self.error(&format!("expect '{word}'"));
+            }
+            self.current += 1;
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            'n' => self.expect("null").map(|_| Json::Null),
+            't' => self.expect("true").map(|_| Json::Bool(true)),
+            'f' => self.expect("false").map(|_| Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => {
+                self.current += 1;
+                let mut array = vec![];
+                self.skip_whitespace();
+                if self.peek() == ']' {
+                    self.current += 1;
+                    return Ok(Json::Array(array));
+                }
+                loop {
+                    array.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        ',' => self.current += 1,
+                        ']' => {
+                            self.current += 1;
+                            return Ok(Json::Array(array));
+                        }
+                        _ => return self.error("expect , or ]"),
+                    }
+                }
+            }
+            '{' => {
+                self.current += 1;
+                let mut fields = vec![];
+                self.skip_whitespace();
+                if self.peek() == '}' {
+                    self.current += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != '"' {
+                        return self.error("expect key");
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    fields.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        ',' => self.current += 1,
+                        '}' => {
+                            self.current += 1;
+                            return Ok(Json::Object(fields));
+                        }
+                        _ => return self.error("expect , or }"),
+                    }
+                }
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = self.current;
+                while "+-.eE".contains(self.peek()) || self.peek().is_ascii_digit() {
+                    self.current += 1;
+                }
+                let number = String::from_iter(&self.source[start..self.current]);
+                if let Some(int) = BigInt::parse(&number) {
+                    return Ok(Json::Integer(int));
+                }
+                match number.parse() {
+                    Ok(num) => Ok(Json::Number(num)),
+                    Err(_) => self.error("bad number"),
+                }
+            }
+            _ => self.error("unexpected character"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        // The start ".
+        self.current += 1;
+        let mut str = String::new();
+        loop {
+            let c = self.peek();
+            self.current += 1;
+            match c {
+                '\0' if self.current > self.source.len() => {
+                    return self.error("unterminated string")
+                }
+                '"' => return Ok(str),
+                '\\' => {
+                    let c = self.peek();
+                    self.current += 1;
+                    match c {
+                        'n' => str.push('\n'),
+                        'r' => str.push('\r'),
+                        't' => str.push('\t'),
+                        'b' => str.push('\u{8}'),
+                        'f' => str.push('\u{c}'),
+                        'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xD800..0xDC00).contains(&code) && self.peek() == '\\' {
+                                self.current += 2;
+                                let low = self.hex4()?;
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            str.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        c => str.push(c),
+                    }
+                }
+                c => str.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        if self.current + 4 > self.source.len() {
+            return self.error("bad \\u escape");
+        }
+        let hex = String::from_iter(&self.source[self.current..self.current + 4]);
+        self.current += 4;
+        match u32::from_str_radix(&hex, 16) {
+            Ok(code) => Ok(code),
+            Err(_) => self.error("bad \\u escape"),
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::fs::OpenOptions;
+use std::io::{stdin, stdout, BufRead, Read, Write};
+use std::path::PathBuf;
+
+const HISTORY_SIZE: usize = 1000;
+
+pub enum ReadLine {
+    Line(String),
+    /// Ctrl-C, the line was dropped.
+    Interrupted,
+    /// Ctrl-D on an empty line or end of input.
+    Eof,
+}
+
+/// Reads lines with cursor movement, history and completion when stdin is a
+/// Linux terminal, plain `read_line` otherwise.
+pub struct LineEditor {
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+}
+
+enum Key {
+    Char(char),
+    Ctrl(u8),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Unknown,
+    Eof,
+}
+
+impl LineEditor {
+    /// Loads history saved by earlier sessions from `history_file`.
+    pub fn new(history_file: Option<PathBuf>) -> Self {
+        let history = history_file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        Self {
+            history,
+            history_file,
+        }
+    }
+
+    /// Adds `line` to the history and appends it to the history file.
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > HISTORY_SIZE {
+            self.history.remove(0);
+        }
+        if let Some(path) = &self.history_file {
+            let file = OpenOptions::new().create(true).append(true).open(path);
+            if let Ok(mut file) = file {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+
+    /// `complete` gets the word before the cursor and returns full words starting with it.
+    pub fn read_line(
+        &mut self,
+        prompt: &str,
+        complete: &dyn Fn(&str) -> Vec<String>,
+    ) -> std::io::Result<ReadLine> {
+        match raw::RawMode::enable() {
+            Some(_raw) => self.edit(prompt, complete),
+            None => {
+                print!("{prompt}");
+                stdout().flush()?;
+                let mut line = String::new();
+                if stdin().lock().read_line(&mut line)? == 0 {
+                    return Ok(ReadLine::Eof);
+                }
+                Ok(ReadLine::Line(
+                    line.trim_end_matches(['\n', '\r']).to_string(),
+                ))
+            }
+        }
+    }
+
+    fn edit(
+        &mut self,
+        prompt: &str,
+        complete: &dyn Fn(&str) -> Vec<String>,
+    ) -> std::io::Result<ReadLine> {
+        let mut out = stdout();
+        let mut line: Vec<char> = vec![];
+        let mut cursor = 0;
+        // Position in history while browsing with Up and Down, the edited line is kept aside.
+        let mut browsing = self.history.len();
+        let mut edited = vec![];
+        let mut ambiguous = false;
+        loop {
+            refresh(&mut out, prompt, &line, cursor)?;
+            let key = read_key()?;
+            if !matches!(key, Key::Tab) {
+                ambiguous = false;
+            }
+            match key {
+                Key::Enter => {
+                    write!(out, "\r\n")?;
+                    out.flush()?;
+                    return Ok(ReadLine::Line(line.into_iter().collect()));
+                }
+                Key::Ctrl(b'c') => {
+                    write!(out, "^C\r\n")?;
+                    return Ok(ReadLine::Interrupted);
+                }
+                Key::Eof => return Ok(ReadLine::Eof),
+                Key::Ctrl(b'd') if line.is_empty() => return Ok(ReadLine::Eof),
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Backspace | Key::Ctrl(b'h') if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete | Key::Ctrl(b'd') if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left | Key::Ctrl(b'b') => cursor = cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl(b'f') => cursor = (cursor + 1).min(line.len()),
+                Key::Home | Key::Ctrl(b'a') => cursor = 0,
+                Key::End | Key::Ctrl(b'e') => cursor = line.len(),
+                Key::Ctrl(b'u') => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::Ctrl(b'k') => line.truncate(cursor),
+                Key::Ctrl(b'w') => {
+                    let mut start = cursor;
+                    while start > 0 && line[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    while start > 0 && !line[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    line.drain(start..cursor);
+                    cursor = start;
+                }
+                Key::Ctrl(b'l') => write!(out, "\x1b[H\x1b[2J")?,
+                Key::Up | Key::Ctrl(b'p') if browsing > 0 => {
+                    if browsing == self.history.len() {
+                        edited = line.clone();
+                    }
+                    browsing -= 1;
+                    line = self.history[browsing].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down | Key::Ctrl(b'n') if browsing < self.history.len() => {
+                    browsing += 1;
+                    line = match self.history.get(browsing) {
+                        Some(entry) => entry.chars().collect(),
+                        None => edited.clone(),
+                    };
+                    cursor = line.len();
+                }
+                Key::Ctrl(b'r') => {
+                    if let Some((found, accept)) = self.search(&mut out)? {
+                        line = found.chars().collect();
+                        cursor = line.len();
+                        if accept {
+                            refresh(&mut out, prompt, &line, cursor)?;
+                            write!(out, "\r\n")?;
+                            return Ok(ReadLine::Line(found));
+                        }
+                    }
+                }
+                Key::Tab => {
+                    let start = word_start(&line, cursor);
+                    let word: String = line[start..cursor].iter().collect();
+                    let mut candidates = complete(&word);
+                    candidates.sort();
+                    candidates.dedup();
+                    let mut rest: Vec<char> = common_prefix(&candidates)
+                        .chars()
+                        .skip(cursor - start)
+                        .collect();
+                    if candidates.len() == 1 {
+                        rest.push(' ');
+                    }
+                    if !rest.is_empty() {
+                        line.splice(cursor..cursor, rest.iter().copied());
+                        cursor += rest.len();
+                    } else if ambiguous {
+                        write!(out, "\r\n{}\r\n", candidates.join("  "))?;
+                    }
+                    // Tab again on the same ambiguous word lists the candidates.
+                    ambiguous = candidates.len() > 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Ctrl-R search backwards through history. Returns the match and whether
+    /// Enter accepted it, `None` when cancelled with Ctrl-G or Ctrl-C.
+    fn search(&self, out: &mut impl Write) -> std::io::Result<Option<(String, bool)>> {
+        let mut query = String::new();
+        let mut from = self.history.len();
+        let mut found: Option<usize> = None;
+        loop {
+            let text = found.map_or("", |i| self.history[i].as_str());
+            write!(out, "\r(reverse-i-search)'{query}': {text}\x1b[K")?;
+            out.flush()?;
+            match read_key()? {
+                Key::Char(c) => {
+                    query.push(c);
+                    from = self.history.len();
+                }
+                Key::Backspace | Key::Ctrl(b'h') => {
+                    query.pop();
+                    from = self.history.len();
+                }
+                Key::Ctrl(b'r') => from = found.unwrap_or(from),
+                Key::Ctrl(b'g') | Key::Ctrl(b'c') | Key::Eof => return Ok(None),
+                Key::Enter => return Ok(found.map(|i| (self.history[i].clone(), true))),
+                _ => return Ok(Some((text.to_string(), false))),
+            }
+            found = self.history[..from]
+                .iter()
+                .rposition(|entry| entry.contains(&query))
+                .or(found.filter(|i| self.history[*i].contains(&query)));
+        }
+    }
+}
+
+fn refresh(
+    out: &mut impl Write,
+    prompt: &str,
+    line: &[char],
+    cursor: usize,
+) -> std::io::Result<()> {
+    let text: String = line.iter().collect();
+    write!(out, "\r{prompt}{text}\x1b[K")?;
+    if cursor < line.len() {
+        write!(out, "\x1b[{}D", line.len() - cursor)?;
+    }
+    out.flush()
+}
+
+/// Start of the name before `cursor`, names end at whitespace, `(`, `)`, `;` and `@`.
+fn word_start(line: &[char], cursor: usize) -> usize {
+    let mut start = cursor;
+    while start > 0 && !line[start - 1].is_whitespace() && !"();@".contains(line[start - 1]) {
+        start -= 1;
+    }
+    start
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let Some(first) = words.first() else {
+        return String::new();
+    };
+    let mut prefix: Vec<char> = first.chars().collect();
+    for word in &words[1..] {
+        let same = prefix
+            .iter()
+            .zip(word.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        prefix.truncate(same);
+    }
+    prefix.into_iter().collect()
+}
+
+fn read_byte() -> std::io::Result<Option<u8>> {
+    let mut byte = [0];
+    Ok(match stdin().lock().read(&mut byte)? {
+        0 => None,
+        _ => Some(byte[0]),
+    })
+}
+
+fn read_key() -> std::io::Result<Key> {
+    let Some(byte) = read_byte()? else {
+        return Ok(Key::Eof);
+    };
+    Ok(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        127 => Key::Backspace,
+        1..=26 => Key::Ctrl(b'a' + byte - 1),
+        27 => match read_byte()? {
+            Some(b'[') | Some(b'O') => {
+                let mut code = vec![];
+                loop {
+                    match read_byte()? {
+                        Some(b) if b.is_ascii_digit() || b == b';' => code.push(b),
+                        Some(b) => break escape(&code, b),
+                        None => break Key::Unknown,
+                    }
+                }
+            }
+            _ => Key::Unknown,
+        },
+        _ if byte < 32 => Key::Unknown,
+        _ => {
+            let len = match byte {
+                0xf0.. => 4,
+                0xe0.. => 3,
+                0xc0.. => 2,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..len {
+                bytes.extend(read_byte()?);
+            }
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            }
+        }
+    })
+}
+
+/// Key of `ESC [ code last`.
+fn escape(code: &[u8], last: u8) -> Key {
+    match (code, last) {
+        (_, b'A') => Key::Up,
+        (_, b'B') => Key::Down,
+        (_, b'C') => Key::Right,
+        (_, b'D') => Key::Left,
+        (_, b'H') | (b"1" | b"7", b'~') => Key::Home,
+        (_, b'F') | (b"4" | b"8", b'~') => Key::End,
+        (b"3", b'~') => Key::Delete,
+        _ => Key::Unknown,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod raw {
+    use std::os::raw::c_int;
+
+    /// `struct termios` of Linux.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Termios {
+        iflag: u32,
+        oflag: u32,
+        cflag: u32,
+        lflag: u32,
+        line: u8,
+        cc: [u8; 32],
+        ispeed: u32,
+        ospeed: u32,
+    }
+
+    extern "C" {
+        fn isatty(fd: c_int) -> c_int;
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, actions: c_int, termios: *const Termios) -> c_int;
+    }
+
+    const ICRNL: u32 = 0o400;
+    const IXON: u32 = 0o2000;
+    const ISIG: u32 = 0o1;
+    const ICANON: u32 = 0o2;
+    const ECHO: u32 = 0o10;
+    const IEXTEN: u32 = 0o100000;
+    const VTIME: usize = 5;
+    const VMIN: usize = 6;
+    const TCSADRAIN: c_int = 1;
+
+    /// Terminal without line buffering and echo until dropped.
+    pub struct RawMode(Termios);
+
+    impl RawMode {
+        /// `None` when stdin or stdout is not a terminal.
+        pub fn enable() -> Option<Self> {
+            // SAFETY: `termios` is only read after `tcgetattr` filled it.
+            unsafe {
+                if isatty(0) == 0 || isatty(1) == 0 {
+                    return None;
+                }
+                let mut termios = std::mem::zeroed::<Termios>();
+                if tcgetattr(0, &mut termios) != 0 {
+                    return None;
+                }
+                let mut raw = termios;
+                raw.iflag &= !(ICRNL | IXON);
+                raw.lflag &= !(ICANON | ECHO | ISIG | IEXTEN);
+                raw.cc[VMIN] = 1;
+                raw.cc[VTIME] = 0;
+                if tcsetattr(0, TCSADRAIN, &raw) != 0 {
+                    return None;
+                }
+                Some(Self(termios))
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: restores the settings read by `enable`.
+            unsafe {
+                tcsetattr(0, TCSADRAIN, &self.0);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod raw {
+    pub struct RawMode;
+
+    impl RawMode {
+        pub fn enable() -> Option<Self> {
+            None
+        }
+    }
+}
@@ -1,101 +1,318 @@
-use crate::interpreter::builtins::{base, math_and_logic};
+use crate::dap::run_dap;
+use crate::debug_cli::DebugCli;
+use crate::dump::run_dump;
+use crate::interpreter::builtins::{base, math, math_and_logic};
 use crate::interpreter::data::Data;
+use crate::interpreter::debugger::Debugger;
+use crate::interpreter::profiler::Profiler;
 use crate::interpreter::Interpreter;
-use crate::parser::{print_ast, Parser};
+use crate::json::{json_object, Json};
+use crate::parser::Parser;
+use crate::project::Project;
+use crate::repl::run_repl;
 use crate::scanner::Scanner;
-use crate::utils::ResultToString;
+use crate::test_runner::run_tests;
+use crate::utils::{print_stack, OptionToString, ResultToString};
 use std::collections::HashMap;
 use std::env::args;
 use std::fs::File;
-use std::io::{stdout, Read, Write};
+use std::io::{stdin, stdout, Read};
 use std::path::Path;
+use std::process::ExitCode;
 
 mod ast;
+mod bigint;
+mod dap;
+mod debug_cli;
+mod dump;
 mod interpreter;
+mod json;
+mod line_editor;
 mod parser;
+mod project;
+mod rational;
+mod regex;
+mod repl;
 mod scanner;
+mod test_runner;
 mod token;
 mod utils;
 
-fn main() -> Result<(), String> {
-    let mut args = args();
-    match args.len() {
-        1 => {
-            return run_repl();
+const USAGE: &str = "Usage: {bin} [command] [args]
+  run [--stack[=human|json]] file.yp|project|- [args...]
+                         run a file, a project or stdin (-), args are in Args
+  file.yp|project [args...]
+                         same as run
+  eval [--stack[=human|json]] -e code [args...]
+                         run code given on the command line
+  check file.yp...       scan and parse files without running them
+  repl [image]           interactive session, the default without arguments
+  debug file.yp          run under the command line debugger
+  profile file.yp [out.folded]
+  image file.yp out.img  run a file and save definitions and stack
+  dump tokens|ast|run-tree file.yp [sexpr|json|dot|puml] [out]
+  test [path...]         run *_test.yp files
+  vendor [project]       copy dependencies to vendor/ and write yap.lock
+  dap                    Debug Adapter Protocol on stdio
+
+Exit codes: 0 success, 1 runtime or other error, 2 usage error, 3 syntax error";
+
+/// Why the process failed, decides the exit code.
+enum Failure {
+    Usage(String),
+    /// Scanner or parser error.
+    Syntax(String),
+    /// Runtime errors and anything else, like a file that can not be read.
+    Error(String),
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::Error(message)
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = args().collect();
+    let bin = args
+        .first()
+        .map_or("rust-yappinglang-interpreter", |b| b.as_str());
+    match run_command(&args[1.min(args.len())..]) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(message)) => {
+            eprintln!(
+                "{message}
+{}",
+                USAGE.replace("{bin}", bin)
+            );
+            ExitCode::from(2)
         }
-        2 => {
-            return run_file(&args.skip(1).next().unwrap());
+        Err(Failure::Syntax(message)) => {
+            eprintln!("syntax error: {message}");
+            ExitCode::from(3)
         }
-        _ => {
-            println!("Usage: {} [file.yp]", args.next().unwrap());
+        Err(Failure::Error(message)) => {
+            eprintln!("error: {message}");
+            ExitCode::from(1)
         }
     }
-    Ok(())
 }
 
-fn run_file(path: &str) -> Result<(), String> {
-    let mut file = File::open(Path::new(path)).str_res()?;
-    let mut source = String::new();
-    file.read_to_string(&mut source).str_res()?;
-    let mut scanner = Scanner::new(&source);
-    let tokens = scanner.scan_tokens()?;
-    //println!("tokens:");
-    //println!("{:?}", tokens);
-    let mut parser = Parser::new(tokens);
-    let ast = parser.build_tree()?;
-    //println!("ast:");
-    //println!("{:?}", ast);
-    //print_ast(&ast,"test.puml")?;
-    let mut intr = Interpreter::new(get_std_defs());
-    let id = intr.load(&ast);
-    intr.run(id).map_err(|e| println!("error:\n{e}"));
-    println!("stack after end:");
-    println!("-----");
-    intr.stack.iter().rev().for_each(|d| println!("{}", d));
-    println!("-----");
-    //println!("blocks:");
-    //println!("{:?}",intr.blocks);
-
-    Ok(())
+/// `args` are the arguments after the program name.
+fn run_command(args: &[String]) -> Result<(), Failure> {
+    let Some(command) = args.first() else {
+        return Ok(run_repl(None)?);
+    };
+    let rest = &args[1..];
+    match (command.as_str(), rest.len()) {
+        ("run", _) => {
+            let (stack, rest) = stack_option(rest)?;
+            let Some((path, script_args)) = rest.split_first() else {
+                return Err(Failure::Usage("run: missing file".to_string()));
+            };
+            run_file(path, script_args, RunMode::Run(stack))
+        }
+        ("eval", _) => {
+            let (stack, rest) = stack_option(rest)?;
+            let [flag, code, script_args @ ..] = rest else {
+                return Err(Failure::Usage("eval: missing -e code".to_string()));
+            };
+            if flag != "-e" {
+                return Err(Failure::Usage(format!("eval: expected -e, found {flag}")));
+            }
+            run_source(code, "", script_args, RunMode::Run(stack))
+        }
+        ("check", 1..) => check(rest),
+        ("repl", 0 | 1) => Ok(run_repl(rest.first().map(|s| s.as_str()))?),
+        ("dump", _) => Ok(run_dump(rest)?),
+        ("image", 2) => Ok(save_image(&rest[0], &rest[1])?),
+        ("dap", 0) => Ok(run_dap()?),
+        ("test", _) => {
+            let paths = if rest.is_empty() {
+                vec![".".to_string()]
+            } else {
+                rest.to_vec()
+            };
+            if run_tests(&paths)? {
+                Ok(())
+            } else {
+                Err(Failure::Error("some tests failed".to_string()))
+            }
+        }
+        ("vendor", 0 | 1) => {
+            let dir = rest.first().map_or(".", |d| d.as_str());
+            Ok(Project::load(Path::new(dir))?.vendor()?)
+        }
+        ("debug", 1) => run_file(&rest[0], &[], RunMode::Debug),
+        ("profile", 1 | 2) => {
+            let folded = rest.get(1).map_or("profile.folded", |p| p.as_str());
+            run_file(&rest[0], &[], RunMode::Profile(folded))
+        }
+        ("help" | "-h" | "--help", _) => {
+            println!("{}", USAGE.replace("{bin}", "rust-yappinglang-interpreter"));
+            Ok(())
+        }
+        ("check" | "repl" | "image" | "dap" | "vendor" | "debug" | "profile", _) => Err(
+            Failure::Usage(format!("{command}: wrong number of arguments")),
+        ),
+        (path, _) if path.starts_with('-') && path != "-" => {
+            Err(Failure::Usage(format!("unknown option {path}")))
+        }
+        (path, _) => run_file(path, rest, RunMode::Run(None)),
+    }
 }
 
-fn run_repl() -> Result<(), String> {
-    let inp = std::io::stdin();
-    let mut intr = Interpreter::new(get_std_defs());
-    loop {
+/// How the stack left at the end is printed.
+#[derive(Clone, Copy)]
+enum StackFormat {
+    /// One value per line, top first.
+    Human,
+    /// Array of values, top first.
+    Json,
+}
+
+/// Leading `--stack` or `--stack=format` of `args`.
+fn stack_option(args: &[String]) -> Result<(Option<StackFormat>, &[String]), Failure> {
+    let format = match args.first().map(|a| a.as_str()) {
+        Some("--stack" | "--stack=human") => StackFormat::Human,
+        Some("--stack=json") => StackFormat::Json,
+        Some(option) if option.starts_with("--stack") => {
+            return Err(Failure::Usage(format!("unknown stack format in {option}")));
+        }
+        _ => return Ok((None, args)),
+    };
+    Ok((Some(format), &args[1..]))
+}
+
+enum RunMode<'a> {
+    /// Prints the final stack when a format is given.
+    Run(Option<StackFormat>),
+    Debug,
+    /// Prints a call report and writes folded stacks to the path.
+    Profile(&'a str),
+}
+
+fn parse(source: &str, file: &str) -> Result<ast::Block, Failure> {
+    let in_file = |e: String| {
+        if file.is_empty() {
+            Failure::Syntax(e)
+        } else {
+            Failure::Syntax(format!("{file}: {e}"))
+        }
+    };
+    let tokens = Scanner::new(source).scan_tokens().map_err(in_file)?;
+    Parser::new(tokens).build_tree().map_err(in_file)
+}
+
+/// Parses every file, reporting all syntax errors before failing.
+fn check(files: &[String]) -> Result<(), Failure> {
+    let mut failed = None;
+    for file in files {
+        let source = std::fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))?;
+        if let Err(Failure::Syntax(e)) = parse(&source, file) {
+            eprintln!("syntax error: {e}");
+            failed = Some(file);
+        }
+    }
+    match failed {
+        Some(file) => Err(Failure::Syntax(format!(
+            "{file} and possibly more files have errors"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// `path` is a file, a project directory whose entry is run, or `-` for stdin.
+fn run_file(path: &str, script_args: &[String], mode: RunMode) -> Result<(), Failure> {
+    if path == "-" {
         let mut source = String::new();
-        print!("> ");
-        stdout().flush().str_res()?;
-        inp.read_line(&mut source).str_res()?;
-        if source.starts_with("!!!") {
-            source = String::new();
-            loop {
-                print!("> ");
-                stdout().flush().str_res()?;
-                let mut source_add = String::new();
-                inp.read_line(&mut source_add).str_res()?;
-                if source_add.starts_with("!!!") {
-                    break;
-                }
-                source.push_str(&source_add);
-            }
+        stdin().read_to_string(&mut source).str_res()?;
+        return run_source(&source, "", script_args, mode);
+    }
+    let entry;
+    let path = if Path::new(path).is_dir() {
+        entry = Project::load(Path::new(path))?.entry;
+        entry.to_str().str_res()?
+    } else {
+        path
+    };
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    run_source(&source, path, script_args, mode)
+}
+
+/// `file` is empty for code that does not come from a file.
+fn run_source(
+    source: &str,
+    file: &str,
+    script_args: &[String],
+    mode: RunMode,
+) -> Result<(), Failure> {
+    let ast = parse(source, file)?;
+    let mut defs = get_std_defs();
+    let script_args = script_args.iter().rev().map(|a| Data::String(a.clone()));
+    defs.insert("Args".to_string(), Data::List(script_args.collect()));
+    let mut intr = Interpreter::new(defs);
+    if !file.is_empty() {
+        project::configure_for(&mut intr, Path::new(file))?;
+    }
+    let stack = match mode {
+        RunMode::Run(stack) => stack,
+        RunMode::Debug => {
+            intr.set_debugger(Debugger::new(Box::new(DebugCli::new(source))));
+            Some(StackFormat::Human)
         }
+        RunMode::Profile(_) => {
+            intr.set_profiler(Profiler::new());
+            Some(StackFormat::Human)
+        }
+    };
+    let id = intr.load(&ast, file);
+    let res = intr.run(id);
+    match stack {
+        Some(StackFormat::Human) => print_stack(&intr.stack, &mut stdout()).str_res()?,
+        Some(StackFormat::Json) => {
+            let values = intr.stack.iter().rev().map(to_json).collect();
+            println!("{}", Json::Array(values));
+        }
+        None => {}
+    }
+    if let (RunMode::Profile(folded), Some(mut profiler)) = (mode, intr.take_profiler()) {
+        profiler.finish();
+        profiler.report(&mut stdout()).str_res()?;
+        profiler
+            .write_folded(&mut File::create(folded).str_res()?)
+            .str_res()?;
+        println!("folded stacks written to {folded}");
+    }
+    Ok(res.str_res()?)
+}
 
-        let mut scanner = Scanner::new(&source);
-        let tokens = scanner.scan_tokens()?;
-        //println!("tokens:");
-        //println!("{:?}", tokens);
-        let mut parser = Parser::new(tokens);
-        let ast = parser.build_tree()?;
-        //println!("ast:");
-        //println!("{:?}", ast);
-        //print_ast(&ast,"test.puml")?;
-        let id = intr.load(&ast);
-        intr.run(id).map_err(|e| println!("error:\n{e}"));
+/// Strings, lists and numbers as themselves, Integers exact at any size, errors as objects,
+/// anything else, Rationals included, as its text.
+fn to_json(data: &Data) -> Json {
+    match data {
+        Data::String(str) => Json::from(str.as_str()),
+        Data::Integer(int) => Json::from(*int),
+        Data::BigInteger(int) => Json::from(int.as_ref().clone()),
+        Data::Decimal(dec) => Json::from(*dec),
+        Data::List(list) => Json::Array(list.iter().rev().map(to_json).collect()),
+        Data::Error(e) => json_object! {"kind" => e.kind.as_str(), "message" => e.message.as_str()},
+        data => Json::from(data.to_string()),
     }
-    Ok(())
+}
+
+/// Runs `path` and saves the resulting definitions and stack to `image`.
+fn save_image(path: &str, image: &str) -> Result<(), String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let tokens = Scanner::new(&source).scan_tokens()?;
+    let ast = Parser::new(tokens).build_tree()?;
+    let mut intr = Interpreter::new(get_std_defs());
+    project::configure_for(&mut intr, Path::new(path))?;
+    let block = intr.load(&ast, path);
+    intr.run(block).str_res()?;
+    intr.save_image(Path::new(image))
 }
 
 fn get_std_defs() -> HashMap<String, Data> {
-    math_and_logic(base(HashMap::new(), ""), "")
+    math(math_and_logic(base(HashMap::new(), ""), ""), "")
 }
@@ -1,10 +1,5 @@
 use crate::ast::{Block, Exp, ExpData};
 use crate::token::{Token, TokenData};
-use crate::utils::ResultToString;
-use std::any::type_name;
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
 
 pub struct Parser {
     tokens: Vec<Token>,
@@ -17,58 +12,84 @@ impl Parser {
     }
 
     pub fn build_tree(mut self) -> Result<Block, String> {
-        return self.build_block(true);
+        self.build_block(true)
     }
 
     fn build_block(&mut self, file: bool) -> Result<Block, String> {
-        let mut lines: Vec<Vec<ExpData>> = vec![vec![]];
+        let mut lines: Vec<Vec<(ExpData, u64)>> = vec![vec![]];
         while !self.is_at_end() {
-            match self.advance().typ.clone() {
+            let token = self.advance().clone();
+            let line = token.line;
+            match token.typ {
                 TokenData::Identifier(name) => {
-                    lines.last_mut().unwrap().push(ExpData::Var(name));
+                    lines.last_mut().unwrap().push((ExpData::Var(name), line));
                 }
                 TokenData::String(str) => {
-                    lines.last_mut().unwrap().push(ExpData::String(str));
+                    lines.last_mut().unwrap().push((ExpData::String(str), line));
                 }
                 TokenData::Integer(int) => {
-                    lines.last_mut().unwrap().push(ExpData::Integer(int));
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push((ExpData::Integer(int), line));
+                }
+                TokenData::BigInteger(int) => {
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push((ExpData::BigInteger(int), line));
+                }
+                TokenData::Rational(rat) => {
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push((ExpData::Rational(rat), line));
                 }
                 TokenData::Decimal(dec) => {
-                    lines.last_mut().unwrap().push(ExpData::Decimal(dec));
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push((ExpData::Decimal(dec), line));
                 }
                 TokenData::Semicolon => {
                     lines.push(vec![]);
                 }
                 TokenData::LeftParen => {
                     let block = self.build_block(false)?;
-                    lines.last_mut().unwrap().push(ExpData::Block(block));
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push((ExpData::Block(block), line));
                 }
                 TokenData::RightParen => {
                     if file {
                         return self.error("to many )");
                     } else {
-                        let exp = flat_lines(lines).map(|e| Box::new(e));
+                        let exp = flat_lines(lines).map(Box::new);
                         return Ok(Block { exp });
                     }
                 }
                 TokenData::Capture => match self.advance().typ.clone() {
                     TokenData::Identifier(name) => {
-                        lines.last_mut().unwrap().push(ExpData::CapturedVar(name));
+                        lines
+                            .last_mut()
+                            .unwrap()
+                            .push((ExpData::CapturedVar(name), line));
                     }
                     _ => return self.error("expect identifier for capture"),
                 },
             }
         }
         if file {
-            let exp = flat_lines(lines).map(|e| Box::new(e));
-            return Ok(Block { exp });
+            let exp = flat_lines(lines).map(Box::new);
+            Ok(Block { exp })
         } else {
-            return self.error("block not ended by )");
+            self.error("block not ended by )")
         }
     }
 
     fn is_at_end(&self) -> bool {
-        return self.current >= self.tokens.len();
+        self.current >= self.tokens.len()
     }
     fn advance(&mut self) -> &Token {
         if self.is_at_end() {
@@ -76,11 +97,11 @@ impl Parser {
         }
         let t = &self.tokens[self.current];
         self.current += 1;
-        return t;
+        t
     }
 
     fn previous(&self) -> &Token {
-        return &self.tokens[self.current - 1];
+        &self.tokens[self.current - 1]
     }
 
     fn error<T>(&self, err: &str) -> Result<T, String> {
@@ -88,18 +109,20 @@ impl Parser {
     }
 }
 
-fn flat_lines(lines: Vec<Vec<ExpData>>) -> Option<Exp> {
+fn flat_lines(lines: Vec<Vec<(ExpData, u64)>>) -> Option<Exp> {
     let mut head = None;
     for line in lines.into_iter() {
-        for exp in line.into_iter().rev() {
+        for (exp, line) in line.into_iter().rev() {
             if let Some(e) = head {
                 head = Some(Exp {
                     data: exp,
+                    line,
                     next_exp: Some(Box::new(e)),
                 });
             } else {
                 head = Some(Exp {
                     data: exp,
+                    line,
                     next_exp: None,
                 });
             }
@@ -107,60 +130,3 @@ fn flat_lines(lines: Vec<Vec<ExpData>>) -> Option<Exp> {
     }
     head
 }
-
-pub fn print_ast(tree: &Block, filepath: &str) -> Result<(), String> {
-    let mut file = File::create(Path::new(filepath)).str_res()?;
-
-    file.write_all("@startuml\n".as_ref()).str_res()?;
-
-    file.write_all("(file)as 0\n".as_ref()).str_res()?;
-
-    if let Some(exp) = &tree.exp {
-        let idx = print_ast_exp(exp, &mut file)?;
-        file.write_all(format!("0-d->{}\n", idx).as_ref())
-            .str_res()?;
-    }
-
-    file.write_all("@enduml\n".as_ref()).str_res()?;
-
-    Ok(())
-}
-fn print_ast_exp(exp: &Exp, file: &mut File) -> Result<usize, String> {
-    file.write_all("(".as_ref()).str_res()?;
-    match &exp.data {
-        ExpData::Var(var) => {
-            file.write_all(var.as_ref()).str_res()?;
-        }
-        ExpData::CapturedVar(var) => {
-            file.write_all(format!("@{var}").as_ref()).str_res()?;
-        }
-        ExpData::Block(_) => {
-            file.write_all("block".as_ref()).str_res()?;
-        }
-        ExpData::Integer(int) => {
-            file.write_all(format!("{int}").as_ref()).str_res()?;
-        }
-        ExpData::Decimal(dec) => {
-            file.write_all(format!("@{dec}").as_ref()).str_res()?;
-        }
-        ExpData::String(str) => {
-            file.write_all(format!("\"{str}").as_ref()).str_res()?;
-        }
-    }
-    let idx = exp as *const Exp as usize;
-    file.write_all(format!(")as {}\n", idx).as_ref())
-        .str_res()?;
-    if let ExpData::Block(block) = &exp.data {
-        if let Some(block_exp) = &block.exp {
-            let block_idx = print_ast_exp(block_exp, file)?;
-            file.write_all(format!("{}-r->{}\n", idx, block_idx).as_ref())
-                .str_res()?;
-        }
-    }
-    if let Some(next) = &exp.next_exp {
-        let next_idx = print_ast_exp(next, file)?;
-        file.write_all(format!("{}-d->{}\n", idx, next_idx).as_ref())
-            .str_res()?;
-    }
-    Ok(idx)
-}
@@ -0,0 +1,490 @@
+use crate::interpreter::Interpreter;
+use crate::utils::ResultToString;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub const MANIFEST: &str = "yap.toml";
+pub const LOCKFILE: &str = "yap.lock";
+const VENDOR: &str = "vendor";
+
+/// Directory with a `yap.toml`:
+///
+/// ```toml
+/// [project]
+/// name = "demo"
+/// entry = "main.yp"
+/// library-path = ["lib"]
+///
+/// [dependencies]
+/// utils = "../utils"
+/// shapes = "../archives/shapes.tar"
+/// ```
+pub struct Project {
+    pub root: PathBuf,
+    pub name: String,
+    pub entry: PathBuf,
+    pub library_path: Vec<PathBuf>,
+    dependencies: Vec<(String, PathBuf)>,
+}
+
+impl Project {
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let root = fs::canonicalize(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        let manifest = root.join(MANIFEST);
+        let source =
+            fs::read_to_string(&manifest).map_err(|e| format!("{}: {e}", manifest.display()))?;
+        let mut project = Self {
+            name: root
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            entry: root.join("main.yp"),
+            library_path: vec![],
+            dependencies: vec![],
+            root,
+        };
+        let in_manifest = |e: String| format!("{}: {e}", manifest.display());
+        for (table, key, value) in parse_toml(&source).map_err(in_manifest)? {
+            match (table.as_str(), key.as_str(), value) {
+                ("project", "name", Value::String(name)) => project.name = name,
+                ("project", "entry", Value::String(entry)) => {
+                    project.entry = project.root.join(entry)
+                }
+                ("project", "library-path", Value::Array(dirs)) => {
+                    project.library_path = dirs.iter().map(|d| project.root.join(d)).collect()
+                }
+                ("dependencies", name, Value::String(path)) => project
+                    .dependencies
+                    .push((name.to_string(), project.root.join(path))),
+                (table, key, _) => {
+                    return Err(in_manifest(format!("unexpected key '{key}' in [{table}]")))
+                }
+            }
+        }
+        Ok(project)
+    }
+
+    /// Project of the nearest directory above `file` with a `yap.toml`.
+    pub fn find(file: &Path) -> Result<Option<Self>, String> {
+        let file = fs::canonicalize(file).map_err(|e| format!("{}: {e}", file.display()))?;
+        for dir in file.ancestors().skip(1) {
+            if dir.join(MANIFEST).is_file() {
+                return Self::load(dir).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Source of every dependency, including dependencies of dependency directories.
+    /// Archives are not opened, so their own dependencies are not followed.
+    fn resolve(&self) -> Result<BTreeMap<String, PathBuf>, String> {
+        let mut packages = BTreeMap::new();
+        self.resolve_into(&mut packages)?;
+        Ok(packages)
+    }
+
+    fn resolve_into(&self, packages: &mut BTreeMap<String, PathBuf>) -> Result<(), String> {
+        for (name, path) in &self.dependencies {
+            let source = fs::canonicalize(path).map_err(|e| {
+                format!(
+                    "dependency '{name}' of {}: {}: {e}",
+                    self.name,
+                    path.display()
+                )
+            })?;
+            match packages.get(name) {
+                Some(known) if *known == source => continue,
+                Some(known) => {
+                    return Err(format!(
+                        "dependency '{name}' found at {} and {}",
+                        known.display(),
+                        source.display()
+                    ))
+                }
+                None => {}
+            }
+            packages.insert(name.clone(), source.clone());
+            if source.join(MANIFEST).is_file() {
+                Self::load(&source)?.resolve_into(packages)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies all dependencies to `vendor/` and records them in `yap.lock`.
+    pub fn vendor(&self) -> Result<(), String> {
+        let packages = self.resolve()?;
+        let vendor = self.root.join(VENDOR);
+        if vendor.is_dir() {
+            for entry in fs::read_dir(&vendor).str_res()? {
+                let path = entry.str_res()?.path();
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if path.is_dir() && !packages.contains_key(&*name) {
+                    fs::remove_dir_all(&path).str_res()?;
+                }
+            }
+        }
+        let mut lock = "# Written by `vendor`, do not edit.\n".to_string();
+        for (name, source) in &packages {
+            let dest = vendor.join(name);
+            if dest.exists() {
+                fs::remove_dir_all(&dest).str_res()?;
+            }
+            if source.is_dir() {
+                copy_dir(source, &dest)?;
+            } else {
+                extract_tar(source, &dest)?;
+            }
+            let dependencies = if source.join(MANIFEST).is_file() {
+                Self::load(source)?.dependencies
+            } else {
+                vec![]
+            };
+            let dependencies: Vec<_> = dependencies.iter().map(|(n, _)| quote(n)).collect();
+            lock.push_str(&format!(
+                "\n[package.{name}]\nsource = {}\nchecksum = \"fnv1a64:{:016x}\"\ndependencies = [{}]\n",
+                quote(&relative(source, &self.root).to_string_lossy()),
+                checksum(&dest)?,
+                dependencies.join(", ")
+            ));
+            println!("vendored {name} from {}", source.display());
+        }
+        fs::write(self.root.join(LOCKFILE), lock).str_res()
+    }
+
+    /// Packages from `yap.lock` when there is one, otherwise from the manifests.
+    /// The lock has to list exactly the dependencies of `yap.toml` and vendored copies
+    /// have to match their checksums.
+    fn packages(&self) -> Result<BTreeMap<String, PathBuf>, String> {
+        let lockfile = self.root.join(LOCKFILE);
+        if !lockfile.is_file() {
+            return self.resolve();
+        }
+        let source = fs::read_to_string(&lockfile).str_res()?;
+        let in_lock = |e: String| format!("{}: {e}", lockfile.display());
+        let mut locked: BTreeMap<String, Locked> = BTreeMap::new();
+        for (table, key, value) in parse_toml(&source).map_err(in_lock)? {
+            let Some(name) = table.strip_prefix("package.") else {
+                continue;
+            };
+            let package = locked.entry(name.to_string()).or_insert_with(|| Locked {
+                source: PathBuf::new(),
+                checksum: None,
+                dependencies: vec![],
+            });
+            match (key.as_str(), value) {
+                ("source", Value::String(path)) => package.source = self.root.join(path),
+                ("checksum", Value::String(checksum)) => package.checksum = Some(checksum),
+                ("dependencies", Value::Array(names)) => package.dependencies = names,
+                (key, _) => return Err(in_lock(format!("unexpected key '{key}' in [{table}]"))),
+            }
+        }
+        let stale = |message: String| in_lock(format!("{message}, run `vendor` again"));
+        for (name, path) in &self.dependencies {
+            let Some(package) = locked.get(name) else {
+                return Err(stale(format!("dependency '{name}' is not locked")));
+            };
+            if let (Ok(path), Ok(source)) =
+                (fs::canonicalize(path), fs::canonicalize(&package.source))
+            {
+                if path != source {
+                    return Err(stale(format!(
+                        "dependency '{name}' is locked from another source"
+                    )));
+                }
+            }
+        }
+        let mut needed: Vec<&String> = self.dependencies.iter().map(|(name, _)| name).collect();
+        let mut packages = BTreeMap::new();
+        while let Some(name) = needed.pop() {
+            if packages.contains_key(name) {
+                continue;
+            }
+            let Some(package) = locked.get(name) else {
+                return Err(stale(format!("dependency '{name}' is not locked")));
+            };
+            needed.extend(&package.dependencies);
+            packages.insert(name.clone(), package.source.clone());
+        }
+        if let Some(name) = locked.keys().find(|name| !packages.contains_key(*name)) {
+            return Err(stale(format!("package '{name}' is no longer a dependency")));
+        }
+        for (name, package) in &locked {
+            let vendored = self.root.join(VENDOR).join(name);
+            if !vendored.is_dir() {
+                return Err(stale(format!("{} is missing", vendored.display())));
+            }
+            let checksum = format!("fnv1a64:{:016x}", checksum(&vendored)?);
+            if package.checksum.as_ref() != Some(&checksum) {
+                return Err(stale(format!(
+                    "{} does not match its checksum",
+                    vendored.display()
+                )));
+            }
+        }
+        Ok(packages)
+    }
+
+    /// Gives `intr` the library path and packages, vendored copies are used when present.
+    pub fn configure(&self, intr: &mut Interpreter) -> Result<(), String> {
+        for dir in &self.library_path {
+            intr.add_library_path(dir.clone());
+        }
+        for (name, source) in self.packages()? {
+            let vendored = self.root.join(VENDOR).join(&name);
+            let dir = if vendored.is_dir() {
+                vendored
+            } else if source.is_dir() {
+                source
+            } else {
+                return Err(format!(
+                    "dependency '{name}' is an archive, run `vendor` first"
+                ));
+            };
+            let entry = if dir.join(MANIFEST).is_file() {
+                Self::load(&dir)?.entry
+            } else {
+                dir.join("main.yp")
+            };
+            intr.add_package(name, dir, entry);
+        }
+        Ok(())
+    }
+}
+
+/// Configures `intr` with the project containing `file`, if any.
+pub fn configure_for(intr: &mut Interpreter, file: &Path) -> Result<(), String> {
+    if let Some(project) = Project::find(file)? {
+        project.configure(intr)?;
+    }
+    Ok(())
+}
+
+/// A `[package.name]` table of `yap.lock`.
+struct Locked {
+    source: PathBuf,
+    checksum: Option<String>,
+    dependencies: Vec<String>,
+}
+
+enum Value {
+    String(String),
+    Array(Vec<String>),
+}
+
+/// `(table, key, value)` of the TOML subset used by manifests and lockfiles:
+/// `[table]` headers and `key = "string"` or `key = ["string", ...]` lines.
+fn parse_toml(source: &str) -> Result<Vec<(String, String, Value)>, String> {
+    let mut entries = vec![];
+    let mut table = String::new();
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {message}", i + 1);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name.split('#').next().unwrap_or_default().trim_end();
+            let name = name.strip_suffix(']').ok_or_else(|| error("expected ]"))?;
+            table = name.trim().to_string();
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected key = value"))?;
+        let key = key.trim().trim_matches('"').to_string();
+        let value = value.trim_start();
+        let (value, rest) = if let Some(mut rest) = value.strip_prefix('[') {
+            let mut items = vec![];
+            loop {
+                rest = rest.trim_start();
+                if let Some(r) = rest.strip_prefix(']') {
+                    break (Value::Array(items), r);
+                }
+                let (item, r) = parse_string(rest).ok_or_else(|| error("expected string"))?;
+                items.push(item);
+                rest = r.trim_start();
+                rest = rest.strip_prefix(',').unwrap_or(rest);
+            }
+        } else {
+            let (s, rest) = parse_string(value).ok_or_else(|| error("expected string"))?;
+            (Value::String(s), rest)
+        };
+        let rest = rest.trim();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(error(&format!("unexpected '{rest}'")));
+        }
+        entries.push((table.clone(), key, value));
+    }
+    Ok(entries)
+}
+
+/// Quoted string at the start of `source` and the text after it.
+fn parse_string(source: &str) -> Option<(String, &str)> {
+    let mut chars = source.strip_prefix('"')?.char_indices();
+    let mut s = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((s, &source[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => s.push('\n'),
+                't' => s.push('\t'),
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+    None
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `path` as seen from `base`, both canonical.
+fn relative(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<_> = path.components().collect();
+    let base: Vec<_> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for c in &path[common..] {
+        relative.push(c);
+    }
+    relative
+}
+
+fn skipped(name: &str) -> bool {
+    name.starts_with('.') || name == "target" || name == VENDOR
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).str_res()?;
+    for entry in fs::read_dir(from).str_res()? {
+        let path = entry.str_res()?.path();
+        let name = path.file_name().unwrap_or_default();
+        if skipped(&name.to_string_lossy()) {
+            continue;
+        }
+        if path.is_dir() {
+            copy_dir(&path, &to.join(name))?;
+        } else {
+            fs::copy(&path, to.join(name)).str_res()?;
+        }
+    }
+    Ok(())
+}
+
+/// FNV-1a of every file path and content under `dir`, in path order.
+fn checksum(dir: &Path) -> Result<u64, String> {
+    fn files(dir: &Path, base: &Path, out: &mut Vec<PathBuf>) -> Result<(), String> {
+        for entry in fs::read_dir(dir).str_res()? {
+            let path = entry.str_res()?.path();
+            if path.is_dir() {
+                files(&path, base, out)?;
+            } else {
+                out.push(path.strip_prefix(base).unwrap_or(&path).to_path_buf());
+            }
+        }
+        Ok(())
+    }
+    let mut paths = vec![];
+    files(dir, dir, &mut paths)?;
+    paths.sort();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes.iter().chain(&[0]) {
+            hash = (hash ^ *b as u64).wrapping_mul(0x100000001b3);
+        }
+    };
+    for path in paths {
+        feed(path.to_string_lossy().replace('\\', "/").as_bytes());
+        feed(&fs::read(dir.join(&path)).str_res()?);
+    }
+    Ok(hash)
+}
+
+/// Extracts an uncompressed tar archive. When all entries are in one
+/// top directory, its content is extracted instead.
+fn extract_tar(archive: &Path, to: &Path) -> Result<(), String> {
+    let bad = |message: &str| format!("{}: {message}", archive.display());
+    let data = fs::read(archive).str_res()?;
+    let mut entries = vec![];
+    let mut long_name = None;
+    let mut pos = 0;
+    while pos + 512 <= data.len() {
+        let header = &data[pos..pos + 512];
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let field = |range: std::ops::Range<usize>| {
+            let bytes = &header[range];
+            let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..end]).to_string()
+        };
+        let size = u64::from_str_radix(field(124..136).trim(), 8)
+            .map_err(|_| bad("bad entry size"))? as usize;
+        let start = pos + 512;
+        let content = data
+            .get(start..start + size)
+            .ok_or_else(|| bad("truncated"))?;
+        pos = start + size.div_ceil(512) * 512;
+        let mut name = match field(345..500) {
+            prefix if !prefix.is_empty() && &header[257..262] == b"ustar" => {
+                format!("{prefix}/{}", field(0..100))
+            }
+            _ => field(0..100),
+        };
+        match header[156] {
+            b'L' => {
+                let end = content
+                    .iter()
+                    .position(|b| *b == 0)
+                    .unwrap_or(content.len());
+                long_name = Some(String::from_utf8_lossy(&content[..end]).to_string());
+                continue;
+            }
+            b'0' | 0 | b'5' => {}
+            _ => continue,
+        }
+        if let Some(long) = long_name.take() {
+            name = long;
+        }
+        let path = PathBuf::from(&name);
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(bad(&format!("entry '{name}' is outside the archive")));
+        }
+        entries.push((path, header[156] == b'5', content));
+    }
+    let top = entries
+        .first()
+        .and_then(|(p, _, _)| p.components().next())
+        .map(|c| PathBuf::from(c.as_os_str()));
+    let strip = top.filter(|top| {
+        entries
+            .iter()
+            .all(|(p, dir, _)| p.starts_with(top) && (*dir || p.components().count() > 1))
+    });
+    for (path, dir, content) in entries {
+        let path = match &strip {
+            Some(top) => path.strip_prefix(top).unwrap_or(&path).to_path_buf(),
+            None => path,
+        };
+        let dest = to.join(path);
+        if dir {
+            fs::create_dir_all(&dest).str_res()?;
+        } else {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).str_res()?;
+            }
+            fs::write(&dest, content).str_res()?;
+        }
+    }
+    fs::create_dir_all(to).str_res()
+}
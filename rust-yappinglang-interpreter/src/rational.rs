@@ -0,0 +1,251 @@
+use crate::bigint::BigInt;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+/// Exact fraction in lowest terms, the denominator is positive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rational {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl From<BigInt> for Rational {
+    fn from(value: BigInt) -> Self {
+        Rational {
+            numerator: value,
+            denominator: BigInt::from(1),
+        }
+    }
+}
+
+impl Rational {
+    /// `None` when `denominator` is zero.
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Option<Self> {
+        if denominator.is_zero() {
+            return None;
+        }
+        let divisor = if numerator.is_zero() {
+            denominator.abs()
+        } else {
+            numerator.gcd(&denominator)
+        };
+        let (mut numerator, mut denominator) = (
+            numerator.checked_div(&divisor)?,
+            denominator.checked_div(&divisor)?,
+        );
+        if denominator.is_negative() {
+            numerator = numerator.checked_neg()?;
+            denominator = denominator.checked_neg()?;
+        }
+        Some(Rational {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// `-12.50`, `7` or `1/3`.
+    pub fn parse(str: &str) -> Option<Self> {
+        if let Some((numerator, denominator)) = str.split_once('/') {
+            return Rational::new(BigInt::parse(numerator)?, BigInt::parse(denominator)?);
+        }
+        let (whole, fraction) = str.split_once('.').unwrap_or((str, ""));
+        if whole.is_empty() || whole == "-" {
+            return None;
+        }
+        let digits = BigInt::parse(&format!("{whole}{fraction}"))?;
+        Rational::new(digits, BigInt::from(10).pow(fraction.len() as u32))
+    }
+
+    /// Exact value of the shortest decimal that reads back as `value`, so `0.1` is 1/10.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let str = format!("{value}");
+        Rational::parse(&str)
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &BigInt {
+        &self.denominator
+    }
+
+    pub fn abs(&self) -> Self {
+        Rational {
+            numerator: self.numerator.abs(),
+            denominator: self.denominator.clone(),
+        }
+    }
+
+    pub fn floor(&self) -> BigInt {
+        self.numerator
+            .div_mod_floor(&self.denominator)
+            .map_or_else(|| BigInt::from(0), |(quotient, _)| quotient)
+    }
+
+    pub fn ceil(&self) -> BigInt {
+        let ceil = self.checked_neg().map(|neg| neg.floor().checked_neg());
+        ceil.flatten().unwrap_or_else(|| BigInt::from(0))
+    }
+
+    /// Halves round away from zero.
+    pub fn round(&self) -> BigInt {
+        let half = Rational::new(BigInt::from(1), BigInt::from(2));
+        let rounded = half.and_then(|half| {
+            if self.numerator.is_negative() {
+                Some(self.checked_sub(&half)?.ceil())
+            } else {
+                Some(self.checked_add(&half)?.floor())
+            }
+        });
+        rounded.unwrap_or_else(|| BigInt::from(0))
+    }
+
+    /// `None` for a negative power of zero.
+    pub fn pow(&self, exponent: i64) -> Option<Self> {
+        let power = u32::try_from(exponent.unsigned_abs()).ok()?;
+        let (numerator, denominator) = (self.numerator.pow(power), self.denominator.pow(power));
+        if exponent < 0 {
+            Rational::new(denominator, numerator)
+        } else {
+            Rational::new(numerator, denominator)
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let (numerator, denominator) = (self.numerator.to_f64(), self.denominator.to_f64());
+        if numerator.is_finite() && denominator.is_finite() {
+            return numerator / denominator;
+        }
+        // Too large for f64 on their own, the value itself may not be.
+        let Some((whole, remainder)) = self.numerator.div_rem(&self.denominator) else {
+            return f64::NAN;
+        };
+        let scale = BigInt::from(1 << 62).checked_mul(&BigInt::from(4));
+        let fraction = scale
+            .and_then(|scale| {
+                remainder
+                    .checked_mul(&scale)?
+                    .checked_div(&self.denominator)
+            })
+            .map_or(0.0, |f| f.to_f64() / 18446744073709551616.0);
+        whole.to_f64() + fraction
+    }
+
+    /// Rounded half away from zero to `places` digits after the point.
+    pub fn to_fixed(&self, places: u32) -> String {
+        let scaled = self
+            .numerator
+            .abs()
+            .checked_mul(&BigInt::from(10).pow(places));
+        let two = BigInt::from(2);
+        let rounded = scaled
+            .and_then(|s| s.checked_mul(&two)?.checked_add(&self.denominator))
+            .and_then(|s| s.checked_div(&self.denominator.checked_mul(&two)?))
+            .unwrap_or_else(|| BigInt::from(0));
+        let mut digits = rounded.to_string();
+        if digits.len() <= places as usize {
+            digits = format!("{}{digits}", "0".repeat(places as usize + 1 - digits.len()));
+        }
+        let point = digits.len() - places as usize;
+        let sign = if self.numerator.is_negative() && !rounded.is_zero() {
+            "-"
+        } else {
+            ""
+        };
+        if places == 0 {
+            format!("{sign}{digits}")
+        } else {
+            format!("{sign}{}.{}", &digits[..point], &digits[point..])
+        }
+    }
+
+    /// Digits after the point when the value has a finite decimal expansion.
+    fn decimal_places(&self) -> Option<u32> {
+        let mut denominator = self.denominator.clone();
+        let (mut twos, mut fives) = (0, 0);
+        for (factor, count) in [(2, &mut twos), (5, &mut fives)] {
+            let factor = BigInt::from(factor);
+            while let Some((quotient, remainder)) = denominator.div_rem(&factor) {
+                if !remainder.is_zero() {
+                    break;
+                }
+                denominator = quotient;
+                *count += 1;
+            }
+        }
+        (denominator == BigInt::from(1)).then_some(u32::max(twos, fives))
+    }
+
+    // The `checked_` methods have the names of the `i64` ones, so operators can use either.
+    // Only division by zero fails.
+
+    pub fn checked_neg(&self) -> Option<Self> {
+        Some(Rational {
+            numerator: self.numerator.checked_neg()?,
+            denominator: self.denominator.clone(),
+        })
+    }
+
+    pub fn checked_add(&self, other: &Rational) -> Option<Self> {
+        Rational::new(
+            self.numerator
+                .checked_mul(&other.denominator)?
+                .checked_add(&other.numerator.checked_mul(&self.denominator)?)?,
+            self.denominator.checked_mul(&other.denominator)?,
+        )
+    }
+
+    pub fn checked_sub(&self, other: &Rational) -> Option<Self> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    pub fn checked_mul(&self, other: &Rational) -> Option<Self> {
+        Rational::new(
+            self.numerator.checked_mul(&other.numerator)?,
+            self.denominator.checked_mul(&other.denominator)?,
+        )
+    }
+
+    pub fn checked_div(&self, other: &Rational) -> Option<Self> {
+        Rational::new(
+            self.numerator.checked_mul(&other.denominator)?,
+            self.denominator.checked_mul(&other.numerator)?,
+        )
+    }
+
+    /// `self - other * q` with `q` the quotient rounded toward zero, like `i64`.
+    pub fn checked_rem(&self, other: &Rational) -> Option<Self> {
+        let quotient = self.checked_div(other)?;
+        let whole = quotient.numerator.checked_div(&quotient.denominator)?;
+        self.checked_sub(&other.checked_mul(&Rational::from(whole))?)
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let left = self.numerator.checked_mul(&other.denominator);
+        let right = other.numerator.checked_mul(&self.denominator);
+        left.cmp(&right)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// `1.25r` when the decimal expansion is finite, else `1/3r`, both readable as literals.
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.decimal_places() {
+            Some(0) => f.write_fmt(format_args!("{}r", self.numerator)),
+            Some(places) => f.write_fmt(format_args!("{}r", self.to_fixed(places))),
+            None => f.write_fmt(format_args!("{}/{}r", self.numerator, self.denominator)),
+        }
+    }
+}
@@ -0,0 +1,705 @@
+/// Regular expressions run by a Pike VM, a Thompson NFA with captures,
+/// so matching is linear in the text whatever the pattern.
+/// Patterns and texts are matched by chars, positions are char indexes.
+#[derive(Debug)]
+pub struct Regex {
+    program: Vec<Inst>,
+    /// Capture groups, without the whole match.
+    groups: usize,
+    /// Saved positions, two per capture and one per repeat.
+    slots: usize,
+}
+
+/// Start and end of the whole match and then of every group, `None` when a group took no part.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+const MAX_REPEAT: u32 = 1000;
+const MAX_PROGRAM: usize = 100_000;
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    /// Any char but a newline.
+    Any,
+    Class(Class),
+    /// Try both, the first is preferred.
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    /// Jumps when the position equals the saved one, so a repeat stops after an empty
+    /// iteration like in backtracking engines.
+    JumpIfEmpty(usize, usize),
+    Assert(Assertion),
+    Match,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Assertion {
+    Start,
+    End,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    /// `\d`, `\w` or `\s`, the bool is true for the negated `\D`, `\W` or `\S`.
+    Named(char, bool),
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+impl Regex {
+    /// `(?i)` at the start ignores case.
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let (pattern, ignore_case) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (rest, true),
+            None => (pattern, false),
+        };
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            current: 0,
+            groups: 0,
+            ignore_case,
+        };
+        let node = parser.alternate()?;
+        if parser.current < parser.chars.len() {
+            return Err(format!("unmatched ')' at {}", parser.current));
+        }
+        let mut compiler = Compiler {
+            program: vec![],
+            slots: 2 * (parser.groups + 1),
+        };
+        compiler.emit(Inst::Save(0));
+        compiler.node(&node)?;
+        compiler.emit(Inst::Save(1));
+        compiler.emit(Inst::Match);
+        Ok(Regex {
+            program: compiler.program,
+            groups: parser.groups,
+            slots: compiler.slots,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        self.captures_at(&chars, 0).is_some()
+    }
+
+    /// Leftmost match starting at char `start` or later, alternatives and greedy repeats preferred
+    /// like in Perl.
+    pub fn captures_at(&self, chars: &[char], start: usize) -> Option<Captures> {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut matched = None;
+        for pos in start..=chars.len() {
+            if matched.is_none() {
+                self.add_thread(&mut current, 0, pos, chars, vec![None; self.slots]);
+            }
+            if current.list.is_empty() && matched.is_some() {
+                break;
+            }
+            for (pc, saved) in std::mem::take(&mut current.list) {
+                let step = match &self.program[pc] {
+                    Inst::Match => {
+                        matched = Some(saved);
+                        break;
+                    }
+                    Inst::Char(c) => chars.get(pos) == Some(c),
+                    Inst::Any => chars.get(pos).is_some_and(|c| *c != '\n'),
+                    Inst::Class(class) => chars.get(pos).is_some_and(|c| class.matches(*c)),
+                    _ => false,
+                };
+                if step {
+                    self.add_thread(&mut next, pc + 1, pos + 1, chars, saved);
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        matched.map(|saved| {
+            saved[..2 * (self.groups + 1)]
+                .chunks(2)
+                .map(|pair| Some((pair[0]?, pair[1]?)))
+                .collect()
+        })
+    }
+
+    /// Matches from left to right without overlaps, an empty match moves on by one char.
+    pub fn captures_all(&self, chars: &[char]) -> Vec<Captures> {
+        let mut all = vec![];
+        let mut start = 0;
+        while start <= chars.len() {
+            let Some(captures) = self.captures_at(chars, start) else {
+                break;
+            };
+            let (begin, end) = captures[0].unwrap_or((start, start));
+            start = if end == begin { end + 1 } else { end };
+            all.push(captures);
+        }
+        all
+    }
+
+    /// Every match replaced by `replacement`, where `$0` is the match, `$1` or `${1}` a group
+    /// and `$$` a dollar.
+    pub fn replace_all(&self, text: &str, replacement: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        let mut last = 0;
+        for captures in self.captures_all(&chars) {
+            let Some((begin, end)) = captures[0] else {
+                continue;
+            };
+            out.extend(&chars[last..begin]);
+            expand(replacement, &captures, &chars, &mut out);
+            last = end;
+        }
+        out.extend(&chars[last..]);
+        out
+    }
+
+    /// Follows jumps, splits, saves and assertions, so the list only has threads waiting for a char.
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        pos: usize,
+        chars: &[char],
+        mut saved: Vec<Option<usize>>,
+    ) {
+        if !threads.visit(pc) {
+            return;
+        }
+        match &self.program[pc] {
+            Inst::Jump(to) => self.add_thread(threads, *to, pos, chars, saved),
+            Inst::Split(first, second) => {
+                self.add_thread(threads, *first, pos, chars, saved.clone());
+                self.add_thread(threads, *second, pos, chars, saved);
+            }
+            Inst::Save(slot) => {
+                saved[*slot] = Some(pos);
+                self.add_thread(threads, pc + 1, pos, chars, saved);
+            }
+            Inst::JumpIfEmpty(slot, to) => {
+                let next = if saved[*slot] == Some(pos) {
+                    *to
+                } else {
+                    pc + 1
+                };
+                self.add_thread(threads, next, pos, chars, saved);
+            }
+            Inst::Assert(assertion) => {
+                if assertion.holds(chars, pos) {
+                    self.add_thread(threads, pc + 1, pos, chars, saved);
+                }
+            }
+            _ => threads.list.push((pc, saved)),
+        }
+    }
+}
+
+fn expand(replacement: &str, captures: &Captures, chars: &[char], out: &mut String) {
+    let mut rest = replacement.chars().peekable();
+    while let Some(c) = rest.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let braced = rest.peek() == Some(&'{');
+        if braced {
+            rest.next();
+        }
+        let mut digits = String::new();
+        while let Some(d) = rest.peek().filter(|d| d.is_ascii_digit()) {
+            digits.push(*d);
+            rest.next();
+        }
+        if braced {
+            rest.next_if_eq(&'}');
+        }
+        match digits.parse::<usize>() {
+            Ok(group) => {
+                if let Some(Some((begin, end))) = captures.get(group) {
+                    out.extend(&chars[*begin..*end]);
+                }
+            }
+            Err(_) => {
+                rest.next_if_eq(&'$');
+                out.push('$');
+            }
+        }
+    }
+}
+
+/// Threads of one position in priority order, each program counter at most once.
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    visited: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads {
+            list: vec![],
+            visited: vec![false; len],
+        }
+    }
+
+    fn visit(&mut self, pc: usize) -> bool {
+        !std::mem::replace(&mut self.visited[pc], true)
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.visited.fill(false);
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Assertion {
+    fn holds(self, chars: &[char], pos: usize) -> bool {
+        let boundary = || {
+            let before = pos > 0 && is_word(chars[pos - 1]);
+            let after = chars.get(pos).is_some_and(|c| is_word(*c));
+            before != after
+        };
+        match self {
+            Assertion::Start => pos == 0,
+            Assertion::End => pos == chars.len(),
+            Assertion::WordBoundary => boundary(),
+            Assertion::NotWordBoundary => !boundary(),
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let found = self.items.iter().any(|item| match item {
+            ClassItem::Range(from, to) => (*from..=*to).contains(&c),
+            ClassItem::Named(name, negated) => named_class(*name, c) != *negated,
+        });
+        found != self.negated
+    }
+}
+
+/// `\d` any Unicode digit, `\w` letters, digits and `_`, `\s` whitespace.
+fn named_class(name: char, c: char) -> bool {
+    match name {
+        'd' => c.is_numeric(),
+        'w' => is_word(c),
+        _ => c.is_whitespace(),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    current: usize,
+    groups: usize,
+    ignore_case: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).copied()
+    }
+
+    fn next_if(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        self.current += found as usize;
+        found
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("{message} at {}", self.current))
+    }
+
+    fn alternate(&mut self) -> Result<Node, String> {
+        let mut alternatives = vec![self.concat()?];
+        while self.next_if('|') {
+            alternatives.push(self.concat()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            Node::Alternate(alternatives)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.repeat(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn repeat(&mut self, mut node: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.counts()? {
+                    Some(counts) => counts,
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            self.current += 1;
+            if matches!(node, Node::Assert(_) | Node::Empty) {
+                return self.error("nothing to repeat");
+            }
+            let greedy = !self.next_if('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    /// `{n}`, `{n,}` or `{n,m}` up to the closing brace, `None` when the brace is a literal.
+    fn counts(&mut self) -> Result<Option<(u32, Option<u32>)>, String> {
+        let rest: String = self.chars[self.current + 1..].iter().collect();
+        let Some(end) = rest.find('}') else {
+            return Ok(None);
+        };
+        let inner = &rest[..end];
+        let number = |s: &str| s.parse::<u32>().ok();
+        let (min, max) = match inner.split_once(',') {
+            None => match number(inner) {
+                Some(n) => (n, Some(n)),
+                None => return Ok(None),
+            },
+            Some((min, "")) => match number(min) {
+                Some(min) => (min, None),
+                None => return Ok(None),
+            },
+            Some((min, max)) => match (number(min), number(max)) {
+                (Some(min), Some(max)) => (min, Some(max)),
+                _ => return Ok(None),
+            },
+        };
+        if max.is_some_and(|max| max < min) || min > MAX_REPEAT || max.unwrap_or(0) > MAX_REPEAT {
+            return self.error(&format!(
+                "bad repeat {{{inner}}}, counts go up to {MAX_REPEAT}"
+            ));
+        }
+        // Leaves the parser on the closing brace, `repeat` steps over it.
+        self.current += inner.chars().count() + 1;
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let Some(c) = self.peek() else {
+            return self.error("unexpected end");
+        };
+        self.current += 1;
+        Ok(match c {
+            '.' => Node::Any,
+            '^' => Node::Assert(Assertion::Start),
+            '$' => Node::Assert(Assertion::End),
+            '[' => Node::Class(self.class()?),
+            '(' => {
+                let index = if self.next_if('?') {
+                    if !self.next_if(':') {
+                        return self.error("only (?: groups and a leading (?i) are supported");
+                    }
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                let node = self.alternate()?;
+                if !self.next_if(')') {
+                    return self.error("missing ')'");
+                }
+                Node::Group(Box::new(node), index)
+            }
+            '*' | '+' | '?' => return self.error("nothing to repeat"),
+            '\\' => match self.escape()? {
+                Escape::Char(c) => self.literal(c),
+                Escape::Named(name, negated) => Node::Class(Class {
+                    negated: false,
+                    items: vec![ClassItem::Named(name, negated)],
+                }),
+                Escape::Assert(assertion) => Node::Assert(assertion),
+            },
+            c => self.literal(c),
+        })
+    }
+
+    fn literal(&self, c: char) -> Node {
+        let (lower, upper) = (lowercase(c), uppercase(c));
+        if self.ignore_case && (lower != c || upper != c) {
+            Node::Class(Class {
+                negated: false,
+                items: [c, lower, upper].map(|c| ClassItem::Range(c, c)).to_vec(),
+            })
+        } else {
+            Node::Char(c)
+        }
+    }
+
+    fn escape(&mut self) -> Result<Escape, String> {
+        let Some(c) = self.peek() else {
+            return self.error("trailing '\\'");
+        };
+        self.current += 1;
+        Ok(match c {
+            'd' | 'w' | 's' => Escape::Named(c, false),
+            'D' | 'W' | 'S' => Escape::Named(c.to_ascii_lowercase(), true),
+            'b' => Escape::Assert(Assertion::WordBoundary),
+            'B' => Escape::Assert(Assertion::NotWordBoundary),
+            'A' => Escape::Assert(Assertion::Start),
+            'z' => Escape::Assert(Assertion::End),
+            'n' => Escape::Char('\n'),
+            't' => Escape::Char('\t'),
+            'r' => Escape::Char('\r'),
+            'u' => {
+                if !self.next_if('{') {
+                    return self.error("expected '{' after \\u");
+                }
+                let digits: String = self.chars[self.current..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_hexdigit())
+                    .collect();
+                self.current += digits.len();
+                let code = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32);
+                match (code, self.next_if('}')) {
+                    (Some(c), true) => Escape::Char(c),
+                    _ => return self.error("bad \\u{...} escape"),
+                }
+            }
+            c if c.is_alphanumeric() => return self.error(&format!("unknown escape \\{c}")),
+            c => Escape::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.next_if('^');
+        let mut items = vec![];
+        let mut first = true;
+        loop {
+            let Some(c) = self.peek() else {
+                return self.error("missing ']'");
+            };
+            self.current += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let from = match c {
+                '\\' => match self.escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Named(name, negated) => {
+                        items.push(ClassItem::Named(name, negated));
+                        continue;
+                    }
+                    Escape::Assert(_) => return self.error("assertion in a class"),
+                },
+                c => c,
+            };
+            let to = if self.peek() == Some('-') && self.chars.get(self.current + 1) != Some(&']') {
+                self.current += 1;
+                match self.peek() {
+                    Some('\\') => {
+                        self.current += 1;
+                        match self.escape()? {
+                            Escape::Char(c) => c,
+                            _ => return self.error("bad range end"),
+                        }
+                    }
+                    Some(c) => {
+                        self.current += 1;
+                        c
+                    }
+                    None => return self.error("missing ']'"),
+                }
+            } else {
+                from
+            };
+            if to < from {
+                return self.error(&format!("bad range {from}-{to}"));
+            }
+            items.push(ClassItem::Range(from, to));
+            if self.ignore_case {
+                for c in [from, to] {
+                    for other in [lowercase(c), uppercase(c)] {
+                        items.push(ClassItem::Range(other, other));
+                    }
+                }
+                if from != to {
+                    items.push(ClassItem::Range(lowercase(from), lowercase(to)));
+                    items.push(ClassItem::Range(uppercase(from), uppercase(to)));
+                }
+            }
+        }
+        Ok(Class { negated, items })
+    }
+}
+
+enum Escape {
+    Char(char),
+    Named(char, bool),
+    Assert(Assertion),
+}
+
+/// Single char case mappings, a char with a longer mapping stays as it is.
+fn lowercase(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+fn uppercase(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+    slots: usize,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> usize {
+        self.program.push(inst);
+        self.program.len() - 1
+    }
+
+    fn node(&mut self, node: &Node) -> Result<(), String> {
+        if self.program.len() > MAX_PROGRAM {
+            return Err("pattern too large".to_string());
+        }
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => {
+                self.emit(Inst::Char(*c));
+            }
+            Node::Any => {
+                self.emit(Inst::Any);
+            }
+            Node::Class(class) => {
+                self.emit(Inst::Class(class.clone()));
+            }
+            Node::Assert(assertion) => {
+                self.emit(Inst::Assert(*assertion));
+            }
+            Node::Group(node, index) => {
+                if let Some(index) = index {
+                    self.emit(Inst::Save(2 * index));
+                    self.node(node)?;
+                    self.emit(Inst::Save(2 * index + 1));
+                } else {
+                    self.node(node)?;
+                }
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.node(node)?;
+                }
+            }
+            Node::Alternate(alternatives) => {
+                let mut jumps = vec![];
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i + 1 < alternatives.len() {
+                        let split = self.emit(Inst::Split(0, 0));
+                        self.node(alternative)?;
+                        jumps.push(self.emit(Inst::Jump(0)));
+                        self.program[split] = Inst::Split(split + 1, self.program.len());
+                    } else {
+                        self.node(alternative)?;
+                    }
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = Inst::Jump(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.node(node)?;
+                }
+                let slot = self.slots;
+                self.slots += 1;
+                let mut exits = vec![];
+                match max {
+                    None => {
+                        // L: split body, end; save; body; jump end if empty; jump L
+                        let split = self.emit(Inst::Split(0, 0));
+                        self.emit(Inst::Save(slot));
+                        self.node(node)?;
+                        exits.push(self.emit(Inst::JumpIfEmpty(slot, 0)));
+                        self.emit(Inst::Jump(split));
+                        exits.push(split);
+                    }
+                    Some(max) => {
+                        for _ in *min..*max {
+                            exits.push(self.emit(Inst::Split(0, 0)));
+                            self.emit(Inst::Save(slot));
+                            self.node(node)?;
+                            exits.push(self.emit(Inst::JumpIfEmpty(slot, 0)));
+                        }
+                    }
+                }
+                let end = self.program.len();
+                for exit in exits {
+                    self.program[exit] = match self.program[exit] {
+                        Inst::JumpIfEmpty(slot, _) => Inst::JumpIfEmpty(slot, end),
+                        _ if *greedy => Inst::Split(exit + 1, end),
+                        _ => Inst::Split(end, exit + 1),
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+}
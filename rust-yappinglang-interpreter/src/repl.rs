@@ -0,0 +1,219 @@
+use crate::dump::{ast_node, sexpr};
+use crate::interpreter::data::Data;
+use crate::interpreter::Interpreter;
+use crate::line_editor::{LineEditor, ReadLine};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::utils::{print_stack, ResultToString};
+use std::collections::HashMap;
+use std::io::stdout;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const COMMANDS: &[(&str, &str)] = &[
+    (":stack", "show the stack, top first"),
+    (":clear", "empty the stack"),
+    (
+        ":defs",
+        "list definitions made in the session and their types",
+    ),
+    (
+        ":type <code>",
+        "type of the value <code> leaves on top, the stack and definitions are kept",
+    ),
+    (":load <file>", "run a file"),
+    (":reload", "run the last loaded file again"),
+    (":save <file>", "write definitions and stack to an image"),
+    (
+        ":restore <file>",
+        "replace definitions and stack with an image",
+    ),
+    (":time", "toggle timing of each input"),
+    (":ast <code>", "show the parsed tree"),
+    (":help", "show this list"),
+];
+
+/// Starts from `image` when given.
+pub fn run_repl(image: Option<&str>) -> Result<(), String> {
+    let mut repl = Repl {
+        intr: Interpreter::new(crate::get_std_defs()),
+        std: crate::get_std_defs(),
+        loaded: None,
+        time: false,
+    };
+    if let Some(image) = image {
+        repl.intr.load_image(Path::new(image))?;
+    }
+    let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".yap_history"));
+    let mut editor = LineEditor::new(history);
+    'repl: loop {
+        let mut source = String::new();
+        loop {
+            let prompt = if source.is_empty() { "> " } else { ". " };
+            let complete = |word: &str| repl.complete(word);
+            match editor.read_line(prompt, &complete).str_res()? {
+                ReadLine::Line(line) => {
+                    editor.add_history(&line);
+                    source.push_str(&line);
+                    source.push('\n');
+                }
+                ReadLine::Interrupted => continue 'repl,
+                ReadLine::Eof => {
+                    println!();
+                    return Ok(());
+                }
+            }
+            if !incomplete(&source) {
+                break;
+            }
+        }
+        let res = match source.trim_start().strip_prefix(':') {
+            Some(command) => repl.command(command),
+            None => repl.eval(&source, ""),
+        };
+        if let Err(e) = res {
+            println!("error:\n{e}");
+        }
+    }
+}
+
+struct Repl {
+    intr: Interpreter,
+    /// Definitions the session started with, hidden by `:defs`.
+    std: HashMap<String, Data>,
+    /// File of the last `:load`.
+    loaded: Option<String>,
+    time: bool,
+}
+
+impl Repl {
+    fn complete(&self, word: &str) -> Vec<String> {
+        if word.starts_with(':') {
+            return COMMANDS
+                .iter()
+                .filter_map(|(usage, _)| usage.split(' ').next())
+                .filter(|name| name.starts_with(word))
+                .map(str::to_string)
+                .collect();
+        }
+        self.intr
+            .defs()
+            .keys()
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect()
+    }
+
+    /// Runs `source` and shows the top of the stack.
+    fn eval(&mut self, source: &str, file: &str) -> Result<(), String> {
+        let start = Instant::now();
+        let res = run(&mut self.intr, source, file);
+        if self.time {
+            println!("time: {:.3} ms", start.elapsed().as_secs_f64() * 1000.0);
+        }
+        res?;
+        if let Some(top) = self.intr.stack.last() {
+            println!("= {top}");
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, command: &str) -> Result<(), String> {
+        let command = command.trim();
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let arg = arg.trim();
+        match name {
+            "stack" => print_stack(&self.intr.stack, &mut stdout()).str_res()?,
+            "clear" => self.intr.stack.clear(),
+            "defs" => {
+                let mut defs: Vec<_> = self
+                    .intr
+                    .defs()
+                    .iter()
+                    .filter(|(name, data)| self.std.get(*name) != Some(*data))
+                    .collect();
+                defs.sort_by(|a, b| a.0.cmp(b.0));
+                for (name, data) in defs {
+                    println!("{name}  {}", data.type_name());
+                }
+            }
+            "type" => {
+                let stack = self.intr.stack.clone();
+                let defs = self.intr.defs().clone();
+                let res = run(&mut self.intr, arg, "");
+                let top = self.intr.stack.last().map(Data::type_name);
+                self.intr.stack = stack;
+                self.intr.set_defs(defs);
+                res?;
+                println!("{}", top.ok_or("empty stack")?);
+            }
+            "load" if !arg.is_empty() => {
+                self.loaded = Some(arg.to_string());
+                self.load(arg)?;
+            }
+            "reload" => match self.loaded.clone() {
+                Some(file) => self.load(&file)?,
+                None => return Err("nothing loaded yet, use :load <file>".to_string()),
+            },
+            "save" if !arg.is_empty() => self.intr.save_image(Path::new(arg))?,
+            "restore" if !arg.is_empty() => self.intr.load_image(Path::new(arg))?,
+            "time" => {
+                self.time = !self.time;
+                println!("timing {}", if self.time { "on" } else { "off" });
+            }
+            "ast" => {
+                let tokens = Scanner::new(arg).scan_tokens()?;
+                print!("{}", sexpr(&ast_node(&Parser::new(tokens).build_tree()?)));
+            }
+            "help" => {
+                for (usage, help) in COMMANDS {
+                    println!("{usage:<17} {help}");
+                }
+            }
+            _ => return Err(format!("unknown command :{command}, see :help")),
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, file: &str) -> Result<(), String> {
+        let source = std::fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))?;
+        self.eval(&source, file)
+    }
+}
+
+fn run(intr: &mut Interpreter, source: &str, file: &str) -> Result<(), String> {
+    let tokens = Scanner::new(source).scan_tokens()?;
+    let ast = Parser::new(tokens).build_tree()?;
+    let block = intr.load(&ast, file);
+    intr.run(block).str_res()
+}
+
+/// `true` while a block or a `""` string is still open, so more lines are needed.
+fn incomplete(source: &str) -> bool {
+    let mut depth = 0;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.peek() == Some(&'"') => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        None => return true,
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            break;
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            '"' => while chars.next_if(|c| !c.is_whitespace()).is_some() {},
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0
+}
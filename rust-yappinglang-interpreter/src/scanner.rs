@@ -1,3 +1,5 @@
+use crate::bigint::BigInt;
+use crate::rational::Rational;
 use crate::token::TokenData::{
     Capture, Decimal, Identifier, Integer, LeftParen, RightParen, Semicolon,
 };
@@ -29,11 +31,11 @@ impl Scanner {
 
             self.scan_token()?;
         }
-        return Ok(self.tokens);
+        Ok(self.tokens)
     }
 
     fn is_at_end(&self) -> bool {
-        return self.current >= self.source.len();
+        self.current >= self.source.len()
     }
 
     fn scan_token(&mut self) -> Result<(), String> {
@@ -72,14 +74,14 @@ impl Scanner {
     fn advance(&mut self) -> char {
         let c = self.source[self.current];
         self.current += 1;
-        return c;
+        c
     }
 
     fn peek(&self) -> char {
         if self.is_at_end() {
             return '\0';
         }
-        return self.source[self.current];
+        self.source[self.current]
     }
     fn add_token(&mut self, typ: TokenData) {
         self.tokens.push(Token {
@@ -91,7 +93,7 @@ impl Scanner {
         // The start ".
         self.advance();
 
-        while !(self.peek() == '"' && self.peek_next() == '"') && !self.is_at_end() {
+        while (self.peek() != '"' || self.peek_next() != '"') && !self.is_at_end() {
             if self.peek() == '\n' {
                 self.line += 1;
             }
@@ -115,6 +117,7 @@ impl Scanner {
     fn error<T>(&self, err: &str) -> Result<T, String> {
         Err(format!("[line {}] Error: {}", self.line, err))
     }
+    #[allow(dead_code)]
     fn hex_integer(&self) -> Result<(), String> {
         todo!()
     }
@@ -122,6 +125,17 @@ impl Scanner {
         while self.peek().is_ascii_digit() {
             self.advance();
         }
+        if let Some(end) = self.rational_end() {
+            let literal = String::from_iter(self.source[self.start..end - 1].iter());
+            self.current = end;
+            return match Rational::parse(&literal) {
+                Some(rat) => {
+                    self.add_token(TokenData::Rational(rat));
+                    Ok(())
+                }
+                None => self.error(&format!("bad rational {literal}r")),
+            };
+        }
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             // Consume the "."
             self.advance();
@@ -135,22 +149,48 @@ impl Scanner {
                 .str_res()?;
             self.add_token(Decimal(dec));
         } else {
-            let int: i64 = String::from_iter(self.source[self.start..self.current].iter())
-                .parse()
-                .str_res()?;
-            self.add_token(Integer(int));
+            let digits = String::from_iter(self.source[self.start..self.current].iter());
+            match digits.parse::<i64>() {
+                Ok(int) => self.add_token(Integer(int)),
+                Err(_) => match BigInt::parse(&digits) {
+                    Some(int) => self.add_token(TokenData::BigInteger(int)),
+                    None => return self.error(&format!("bad integer {digits}")),
+                },
+            }
         }
         Ok(())
     }
 
+    /// End of `.digits` or `/digits` and the `r` of a rational literal after the digits
+    /// at `current`, `None` when no such literal follows.
+    fn rational_end(&self) -> Option<usize> {
+        let digits_end = |from: usize| {
+            let end = (from..self.source.len())
+                .find(|i| !self.source[*i].is_ascii_digit())
+                .unwrap_or(self.source.len());
+            (end > from).then_some(end)
+        };
+        let mut end = self.current;
+        if matches!(self.source.get(end), Some('.' | '/')) {
+            end = digits_end(end + 1)?;
+        }
+        if self.source.get(end) != Some(&'r') {
+            return None;
+        }
+        match self.source.get(end + 1) {
+            Some(c) if !c.is_whitespace() && !"();".contains(*c) => None,
+            _ => Some(end + 1),
+        }
+    }
+
     fn peek_next(&self) -> char {
         if self.current + 1 >= self.source.len() {
             return '\0';
         }
-        return self.source[self.current + 1];
+        self.source[self.current + 1]
     }
     fn identifier(&mut self) {
-        while !self.peek().is_whitespace() && !"();".contains(self.peek()) {
+        while !self.peek().is_whitespace() && !"();".contains(self.peek()) && !self.is_at_end() {
             self.advance();
         }
         let str = String::from_iter(self.source[self.start..self.current].iter());
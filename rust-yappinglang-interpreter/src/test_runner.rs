@@ -0,0 +1,84 @@
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::utils::ResultToString;
+use std::path::{Path, PathBuf};
+
+/// Runs every `*_test.yp` under `paths`, returns `true` when all tests passed.
+///
+/// Each file runs once and collects its tests, then every test runs on an empty stack
+/// with the defs the file made, restored before each test so tests can not affect each other.
+pub fn run_tests(paths: &[String]) -> Result<bool, String> {
+    let mut files = vec![];
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_file() {
+            files.push(path.to_path_buf());
+        } else {
+            find_tests(path, &mut files)?;
+        }
+    }
+    files.sort();
+    let (mut passed, mut failed) = (0, 0);
+    for file in files {
+        println!("{}", file.display());
+        let source = std::fs::read_to_string(&file).str_res()?;
+        let mut intr = match load(&source, &file) {
+            Ok(intr) => intr,
+            Err(e) => {
+                println!("  FAIL  {}\n{}", file.display(), indent(&e));
+                failed += 1;
+                continue;
+            }
+        };
+        let defs = intr.defs().clone();
+        for (name, block) in intr.take_tests() {
+            intr.stack.clear();
+            intr.set_defs(defs.clone());
+            match intr.run_block(&block).str_res() {
+                Ok(()) => {
+                    println!("  ok    {name}");
+                    passed += 1;
+                }
+                Err(e) => {
+                    println!("  FAIL  {name}\n{}", indent(&e));
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!("{passed} passed; {failed} failed");
+    Ok(failed == 0)
+}
+
+fn load(source: &str, file: &Path) -> Result<Interpreter, String> {
+    let tokens = Scanner::new(source).scan_tokens()?;
+    let ast = Parser::new(tokens).build_tree()?;
+    let mut intr = Interpreter::new(crate::get_std_defs());
+    crate::project::configure_for(&mut intr, file)?;
+    let block = intr.load(&ast, &file.to_string_lossy());
+    intr.run(block).str_res()?;
+    Ok(intr)
+}
+
+fn find_tests(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path).str_res()? {
+            let path = entry.str_res()?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !name.starts_with('.') && name != "target" {
+                find_tests(&path, files)?;
+            }
+        }
+    } else if path.to_string_lossy().ends_with("_test.yp") {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|l| format!("        {l}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,3 +1,6 @@
+use crate::bigint::BigInt;
+use crate::rational::Rational;
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub typ: TokenData,
@@ -10,6 +13,10 @@ pub enum TokenData {
     Identifier(String),
     String(String),
     Integer(i64),
+    /// Integer literal too large for `i64`.
+    BigInteger(BigInt),
+    /// `1.25r` or `1/3r`.
+    Rational(Rational),
     Decimal(f64),
 
     // Single-character tokens.
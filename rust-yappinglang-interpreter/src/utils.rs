@@ -1,5 +1,6 @@
 use crate::interpreter::data::Data;
 use std::error::Error;
+use std::io::Write;
 
 pub trait ResultToString<T, E: ToString> {
     fn str_res(self) -> Result<T, String>;
@@ -26,6 +27,9 @@ impl<T> OptionToString<T> for Option<T> {
     }
 }
 
-pub fn print_stack(stack: &Vec<Data>) {
-    stack.iter().rev().for_each(|d| println!("{d}"));
+pub fn print_stack(stack: &[Data], out: &mut dyn Write) -> std::io::Result<()> {
+    for d in stack.iter().rev() {
+        writeln!(out, "{d}")?;
+    }
+    Ok(())
 }
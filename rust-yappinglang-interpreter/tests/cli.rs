@@ -0,0 +1,101 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const BIN: &str = env!("CARGO_BIN_EXE_rust-yappinglang-interpreter");
+
+fn run(args: &[&str], input: &str) -> (Output, String) {
+    let mut child = Command::new(BIN)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    (output, stdout)
+}
+
+#[test]
+fn run_eval_and_stack_formats() {
+    let dir = std::env::temp_dir().join(format!("yp-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.yp");
+    std::fs::write(&file, "Print \"hi ;\nArgs ;\n1.5 ;\n").unwrap();
+    let file = file.to_str().unwrap();
+
+    let (output, stdout) = run(&[file, "a", "b"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout, "'hi'\n");
+    let (_, stdout) = run(&["run", "--stack", file, "a", "b"], "");
+    assert_eq!(stdout, "'hi'\n1.5f\n[ 'a' 'b' ]\n");
+    let (_, stdout) = run(&["run", "--stack=json", file, "a b"], "");
+    assert_eq!(stdout, "'hi'\n[1.5,[\"a b\"]]\n");
+
+    let (output, stdout) = run(&["run", "--stack", "-", "x"], "Print + 1 2 ; Args \"s\n");
+    assert!(output.status.success());
+    assert_eq!(stdout, "3\n[ 'x' ]\n's'\n");
+    let (output, stdout) = run(&["eval", "--stack=json", "-e", "+ 1 2"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout, "[3]\n");
+    let (_, stdout) = run(
+        &[
+            "eval",
+            "--stack=json",
+            "-e",
+            "9007199254740993 100000000000000000000 Neg 9223372036854775807 1/3r 0.5",
+        ],
+        "",
+    );
+    assert_eq!(
+        stdout,
+        "[9007199254740993,100000000000000000000,-9223372036854775807,\"1/3r\",0.5]\n"
+    );
+    let (_, stdout) = run(&["eval", "--stack", "-e", "Args", "z"], "");
+    assert_eq!(stdout, "[ 'z' ]\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn exit_codes() {
+    let dir = std::env::temp_dir().join(format!("yp-cli-codes-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("good.yp"), "Print 1\n").unwrap();
+    std::fs::write(dir.join("bad.yp"), "Print ( 1\n").unwrap();
+    let good = dir.join("good.yp");
+    let bad = dir.join("bad.yp");
+
+    let (output, _) = run(&["eval", "-e", "+ 1 \"a"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "));
+    let (output, _) = run(&["eval", "-e", "/ 1 0"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("division by zero in / 1 0"));
+    let (output, _) = run(&["eval", "-e", "Break"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Break outside a loop"));
+    let (output, stdout) = run(&["-"], "Print 1 ;\n( 2\n");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout, "");
+    let (output, _) = run(&["check", good.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(0));
+    let (output, _) = run(
+        &["check", bad.to_str().unwrap(), good.to_str().unwrap()],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("bad.yp: "));
+    let (output, _) = run(&["eval", "1"], "");
+    assert_eq!(output.status.code(), Some(2));
+    let (output, _) = run(&["run", "--stack=xml", good.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(2));
+    let (output, _) = run(&[dir.join("missing.yp").to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,256 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Scripted Debug Adapter Protocol client talking to `rust-yappinglang-interpreter dap`.
+struct Client {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    seq: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let input = child.stdin.take().unwrap();
+        let output = BufReader::new(child.stdout.take().unwrap());
+        Self {
+            child,
+            input,
+            output,
+            seq: 0,
+        }
+    }
+
+    fn request(&mut self, command: &str, arguments: &str) {
+        self.seq += 1;
+        let body = format!(
+            r#"{{"seq":{},"type":"request","command":"{command}","arguments":{arguments}}}"#,
+            self.seq
+        );
+        write!(self.input, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.input.flush().unwrap();
+    }
+
+    fn message(&mut self) -> String {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            assert_ne!(
+                self.output.read_line(&mut header).unwrap(),
+                0,
+                "adapter exited"
+            );
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.output.read_exact(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    /// Reads messages until one contains `pattern`, skipping output events.
+    fn expect(&mut self, pattern: &str) -> String {
+        loop {
+            let message = self.message();
+            if message.contains(pattern) {
+                return message;
+            }
+            assert!(
+                message.contains(r#""event":"output""#) || message.contains(r#""type":"event""#),
+                "expected {pattern}, got {message}"
+            );
+        }
+    }
+}
+
+#[test]
+fn breakpoint_stack_variables_evaluate() {
+    let dir = std::env::temp_dir().join(format!("yp-dap-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let program = dir.join("double.yp");
+    std::fs::write(
+        &program,
+        "Def-fn \"Double (\n    Def \"N as number;\n    * N 2;\n);\nPrint Double 21;\n",
+    )
+    .unwrap();
+    let program = program.to_str().unwrap().replace('\\', "\\\\");
+
+    let mut client = Client::start();
+    client.request("initialize", r#"{"adapterID":"yappinglang"}"#);
+    client.expect(r#""command":"initialize","success":true"#);
+    client.expect(r#""event":"initialized""#);
+    client.request(
+        "setBreakpoints",
+        &format!(r#"{{"source":{{"path":"{program}"}},"breakpoints":[{{"line":3}}]}}"#),
+    );
+    client.expect(r#""verified":true,"line":3"#);
+    client.request("launch", &format!(r#"{{"program":"{program}"}}"#));
+    client.expect(r#""command":"launch","success":true"#);
+    client.request("configurationDone", "{}");
+    client.expect(r#""command":"configurationDone","success":true"#);
+    client.expect(r#""reason":"breakpoint""#);
+
+    client.request("stackTrace", r#"{"threadId":1}"#);
+    let trace = client.expect(r#""command":"stackTrace""#);
+    assert!(
+        trace.contains(r#""id":0,"name":"Double","line":3"#),
+        "{trace}"
+    );
+    assert!(trace.contains(r#""name":"main","line":5"#), "{trace}");
+
+    client.request("scopes", r#"{"frameId":0}"#);
+    client.expect(r#""name":"Locals","variablesReference":2"#);
+    client.request("variables", r#"{"variablesReference":2}"#);
+    client.expect(r#""name":"N","value":"21""#);
+    for reference in [0, -1, 99] {
+        client.request(
+            "variables",
+            &format!(r#"{{"variablesReference":{reference}}}"#),
+        );
+        let response = client.expect(r#""command":"variables""#);
+        assert!(response.contains(r#""success":false"#), "{response}");
+        assert!(
+            response.contains(&format!("no frame for reference {reference}")),
+            "{response}"
+        );
+    }
+
+    client.request("evaluate", r#"{"expression":"+ N 1","frameId":0}"#);
+    client.expect(r#""result":"22""#);
+    client.request("evaluate", r#"{"expression":"Print \"hi","frameId":0}"#);
+    client.expect(r#""category":"stdout","output":"'hi'\n""#);
+    client.expect(r#""command":"evaluate","success":true"#);
+    client.request("threads", "{}");
+    client.expect(r#""command":"threads","success":true"#);
+
+    client.request("continue", r#"{"threadId":1}"#);
+    client.expect(r#""command":"continue","success":true"#);
+    client.expect(r#""output":"42\n""#);
+    client.expect(r#""event":"exited","body":{"exitCode":0}"#);
+    client.expect(r#""event":"terminated""#);
+    client.request("disconnect", "{}");
+    client.expect(r#""command":"disconnect","success":true"#);
+    assert!(client.child.wait().unwrap().success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn frames_report_their_source() {
+    let dir = std::env::temp_dir().join(format!("yp-dap-sources-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lib.yp"), "Def-fn \"Double (\n    * 2;\n);\n").unwrap();
+    let program = dir.join("main.yp");
+    std::fs::write(
+        &program,
+        "Import \"lib.yp \"Lib. ;\nPrint Lib.Double 21 ;\n",
+    )
+    .unwrap();
+    let program = program.to_str().unwrap().replace('\\', "\\\\");
+    let lib = dir.join("lib.yp");
+    let lib = lib.to_str().unwrap().replace('\\', "\\\\");
+
+    let mut client = Client::start();
+    client.request("initialize", r#"{"adapterID":"yappinglang"}"#);
+    client.expect(r#""command":"initialize","success":true"#);
+    client.expect(r#""event":"initialized""#);
+    client.request(
+        "setFunctionBreakpoints",
+        r#"{"breakpoints":[{"name":"Lib.Double"}]}"#,
+    );
+    client.expect(r#""command":"setFunctionBreakpoints","success":true"#);
+    client.request("launch", &format!(r#"{{"program":"{program}"}}"#));
+    client.expect(r#""command":"launch","success":true"#);
+    client.request("configurationDone", "{}");
+    client.expect(r#""command":"configurationDone","success":true"#);
+    client.expect(r#""reason":"function breakpoint""#);
+
+    client.request("stackTrace", r#"{"threadId":1}"#);
+    let trace = client.expect(r#""command":"stackTrace""#);
+    assert!(
+        trace.contains(&format!(
+            r#""name":"Lib.Double","line":2,"column":1,"source":{{"path":"{lib}"}}"#
+        )),
+        "{trace}"
+    );
+    assert!(
+        trace.contains(&format!(
+            r#""name":"main","line":2,"column":1,"source":{{"path":"{program}"}}"#
+        )),
+        "{trace}"
+    );
+
+    client.request("continue", r#"{"threadId":1}"#);
+    client.expect(r#""command":"continue","success":true"#);
+    client.expect(r#""output":"42\n""#);
+    client.expect(r#""event":"terminated""#);
+    client.request("disconnect", "{}");
+    client.expect(r#""command":"disconnect","success":true"#);
+    assert!(client.child.wait().unwrap().success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn breakpoints_are_per_source() {
+    let dir = std::env::temp_dir().join(format!("yp-dap-per-source-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lib.yp"), "Def-fn \"Double (\n    * 2;\n);\n").unwrap();
+    let program = dir.join("main.yp");
+    std::fs::write(
+        &program,
+        "Import \"lib.yp \"Lib. ;\nPrint Lib.Double 21 ;\n",
+    )
+    .unwrap();
+    let program = program.to_str().unwrap().replace('\\', "\\\\");
+    let lib = dir.join("lib.yp");
+    let lib = lib.to_str().unwrap().replace('\\', "\\\\");
+    let missing = dir.join("missing.yp");
+    let missing = missing.to_str().unwrap().replace('\\', "\\\\");
+
+    let mut client = Client::start();
+    client.request("initialize", r#"{"adapterID":"yappinglang"}"#);
+    client.expect(r#""command":"initialize","success":true"#);
+    client.expect(r#""event":"initialized""#);
+    client.request(
+        "setBreakpoints",
+        &format!(r#"{{"source":{{"path":"{lib}"}},"breakpoints":[{{"line":2}}]}}"#),
+    );
+    client.expect(r#""verified":true,"line":2"#);
+    client.request(
+        "setBreakpoints",
+        &format!(r#"{{"source":{{"path":"{missing}"}},"breakpoints":[{{"line":2}}]}}"#),
+    );
+    client.expect(r#""verified":false,"line":2,"message":"source not found""#);
+    client.request("launch", &format!(r#"{{"program":"{program}"}}"#));
+    client.expect(r#""command":"launch","success":true"#);
+    client.request("configurationDone", "{}");
+    client.expect(r#""command":"configurationDone","success":true"#);
+    client.expect(r#""reason":"breakpoint""#);
+
+    client.request("stackTrace", r#"{"threadId":1}"#);
+    let trace = client.expect(r#""command":"stackTrace""#);
+    assert!(
+        trace.contains(r#""id":0,"name":"Lib.Double","line":2"#),
+        "{trace}"
+    );
+
+    client.request("continue", r#"{"threadId":1}"#);
+    client.expect(r#""command":"continue","success":true"#);
+    let output = client.message();
+    assert!(output.contains(r#""output":"42\n""#), "{output}");
+    client.expect(r#""event":"terminated""#);
+    client.request("disconnect", "{}");
+    client.expect(r#""command":"disconnect","success":true"#);
+    assert!(client.child.wait().unwrap().success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
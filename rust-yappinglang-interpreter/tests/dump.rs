@@ -0,0 +1,69 @@
+use std::process::Command;
+
+const BIN: &str = env!("CARGO_BIN_EXE_rust-yappinglang-interpreter");
+
+fn dump(args: &[&str]) -> String {
+    let output = Command::new(BIN).arg("dump").args(args).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn formats() {
+    let dir = std::env::temp_dir().join(format!("yp-dump-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.yp");
+    std::fs::write(&file, "Def \"A ( @X 1.5 ) ;\nPrint \"hi\n").unwrap();
+    let file = file.to_str().unwrap();
+
+    assert_eq!(
+        dump(&["tokens", file]),
+        "(tokens\n  (identifier Def :1)\n  (string \"A\" :1)\n  (left-paren :1)\n  (capture :1)\n  \
+         (identifier X :1)\n  (decimal 1.5 :1)\n  (right-paren :1)\n  (semicolon :1)\n  \
+         (identifier Print :2)\n  (string \"hi\" :2))\n"
+    );
+    assert_eq!(
+        dump(&["ast", file]),
+        "(block\n  (var Print :2)\n  (string \"hi\" :2)\n  (var Def :1)\n  (string \"A\" :1)\n  \
+         (block :1\n    (capture X :1)\n    (decimal 1.5 :1)))\n"
+    );
+    assert_eq!(
+        dump(&["run-tree", file, "json"]),
+        "{\"kind\":\"block\",\"captures\":[],\"children\":[\
+         {\"kind\":\"var\",\"value\":\"Print\",\"line\":2},\
+         {\"kind\":\"string\",\"value\":\"\\\"hi\\\"\",\"line\":2},\
+         {\"kind\":\"var\",\"value\":\"Def\",\"line\":1},\
+         {\"kind\":\"string\",\"value\":\"\\\"A\\\"\",\"line\":1},\
+         {\"kind\":\"block\",\"line\":1,\"captures\":[\"X\"],\"children\":[\
+         {\"kind\":\"var\",\"value\":\"X\",\"line\":1},\
+         {\"kind\":\"decimal\",\"value\":\"1.5\",\"line\":1}]}]}\n"
+    );
+    let dot = dump(&["run-tree", file, "dot"]);
+    assert!(dot.starts_with("digraph yappinglang {\n"), "{dot}");
+    assert!(
+        dot.contains(
+            "    n5 [label=\"block [X] :1\"];\n    n6 [label=\"var X :1\"];\n    n5 -> n6;\n"
+        ),
+        "{dot}"
+    );
+
+    let out = dir.join("ast.puml");
+    assert_eq!(dump(&["ast", file, "puml", out.to_str().unwrap()]), "");
+    let puml = std::fs::read_to_string(&out).unwrap();
+    assert!(
+        puml.starts_with("@startuml\nusecase \"block\" as n0\n"),
+        "{puml}"
+    );
+    assert!(
+        puml.contains("usecase \"capture X :1\" as n6\nn5 -d-> n6\n"),
+        "{puml}"
+    );
+    assert!(puml.ends_with("n0 -d-> n5\n@enduml\n"), "{puml}");
+
+    let output = Command::new(BIN)
+        .args(["dump", "ast", file, "svg"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
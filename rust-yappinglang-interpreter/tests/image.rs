@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const BIN: &str = env!("CARGO_BIN_EXE_rust-yappinglang-interpreter");
+
+fn repl(args: &[&str], input: &str) -> String {
+    let mut child = Command::new(BIN)
+        .args(args)
+        .env("HOME", std::env::temp_dir().join("yp-image-missing-home"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+}
+
+#[test]
+fn save_and_restore() {
+    let dir = std::env::temp_dir().join(format!("yp-image-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lib.yp"), "Def-fn \"Double ( * 2 ) ;\n").unwrap();
+    std::fs::write(
+        dir.join("main.yp"),
+        "Def \"N 40 ;\nDef-fn \"Add-n ( + @N ) ;\nImport \"lib.yp \"Lib. ;\n\
+         Def \"Plus Get-def \"+ ;\nDef \"L List ( 1 2.5 \"x ) ;\nDef \"Big ( 100000000000000000000 ) ;\nDef \"Third 1/3r ;\n\"on-stack\n",
+    )
+    .unwrap();
+    let image = dir.join("main.img");
+    let status = Command::new(BIN)
+        .args([
+            "image",
+            dir.join("main.yp").to_str().unwrap(),
+            image.to_str().unwrap(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let stdout = repl(
+        &["repl", image.to_str().unwrap()],
+        "Add-n 2\nLib.Double 5\nDo ( Plus 1 2 )\nL\nDo Big\nThird\n",
+    );
+    assert!(
+        stdout.contains(
+            "> = 42\n> = 10\n> = 3\n> = [ 1 2.5f 'x' ]\n> = 100000000000000000000\n> = 1/3r\n"
+        ),
+        "{stdout}"
+    );
+
+    let saved = dir.join("saved.img");
+    let input = format!(":save {}\n", saved.display());
+    repl(&["repl", image.to_str().unwrap()], &input);
+    let stdout = repl(
+        &[],
+        &format!(":restore {}\n:stack\nAdd-n 1\n", saved.display()),
+    );
+    assert!(stdout.contains("'on-stack'\n> = 41\n"), "{stdout}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn corrupt_length_is_truncated() {
+    let dir = std::env::temp_dir().join(format!("yp-image-corrupt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let image = dir.join("corrupt.img");
+    let mut bytes = b"YAPIMG".to_vec();
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(1u64.to_le_bytes());
+    bytes.extend(u64::MAX.to_le_bytes());
+    std::fs::write(&image, bytes).unwrap();
+
+    let output = Command::new(BIN)
+        .args(["repl", image.to_str().unwrap()])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("image file is truncated"), "{stderr}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run(program: &Path, yap_path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+        .arg(program)
+        .env("YAP_PATH", yap_path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn relative_library_and_cycles() {
+    let dir = std::env::temp_dir().join(format!("yp-modules-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::write(
+        dir.join("main.yp"),
+        "Do-local Use-file \"sub/a.yp ;\nPrint Helper 1 ;\nDo-local Use-file \"lib.yp ;\nPrint Lib ;\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("sub").join("a.yp"),
+        "Do-local Use-file \"b.yp ;\nDef-fn \"Helper ( + B ) ;\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("sub").join("b.yp"), "Def \"B 100 ;\n").unwrap();
+    std::fs::write(dir.join("lib").join("lib.yp"), "Def \"Lib 7 ;\n").unwrap();
+    let stdout = String::from_utf8(run(&dir.join("main.yp"), &dir.join("lib")).stdout).unwrap();
+    assert!(stdout.starts_with("101\n7\n"), "{stdout}");
+
+    std::fs::write(dir.join("x.yp"), "Do Use-file \"y.yp ;\n").unwrap();
+    std::fs::write(dir.join("y.yp"), "Do Use-file \"x.yp ;\n").unwrap();
+    let output = run(&dir.join("x.yp"), &dir);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("import cycle: "), "{stderr}");
+    assert!(stderr.contains("x.yp -> "), "{stderr}");
+    assert!(stderr.contains("y.yp -> "), "{stderr}");
+
+    std::fs::write(dir.join("bad.yp"), "Def \"Ok 1 ;\nMissing ;\n").unwrap();
+    std::fs::write(dir.join("uses_bad.yp"), "Do Use-file \"bad.yp ;\n").unwrap();
+    let stderr = String::from_utf8(run(&dir.join("uses_bad.yp"), &dir).stderr).unwrap();
+    assert!(
+        stderr.contains("bad.yp:2: variable 'Missing' not found"),
+        "{stderr}"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn import_with_prefix_and_exports() {
+    let dir = std::env::temp_dir().join(format!("yp-import-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("lib.yp"),
+        "Export \"Twice ;\nDef-fn \"Double ( * 2 ) ;\nDef-fn \"Twice ( Double Double ) ;\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("main.yp"),
+        "Import \"lib.yp \"Lib. ;\nPrint Lib.Twice 3 ;\nPrint Has-def \"Lib.Double ;\n\
+         Import \"math \"Math. ;\nPrint Math.+ 1 2 ;\nImport-only \"lib.yp List ( \"Twice ) ;\nPrint Twice 1 ;\n\
+         Import-only \"lib.yp List ( \"Double ) ;\n",
+    )
+    .unwrap();
+    let output = run(&dir.join("main.yp"), &dir);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "12\n0\n3\n4\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("'Double' is not exported by lib.yp"),
+        "{stderr}"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
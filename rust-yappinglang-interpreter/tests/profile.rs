@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+#[test]
+fn profile_recursive_script() {
+    let dir = std::env::temp_dir().join(format!("yp-profile-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("down.yp");
+    std::fs::write(
+        &file,
+        "Def-fn \"Down ( Def \"N ; Do If (> N 0) ( Down - N 1 ) ( ) ) ;\nDown 2 ;\n",
+    )
+    .unwrap();
+    let folded = dir.join("down.folded");
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+        .args(["profile", file.to_str().unwrap(), folded.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let calls: HashMap<_, _> = stdout
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let calls = fields.first()?.parse::<u64>().ok()?;
+            Some((fields[3..].join(" "), calls))
+        })
+        .collect();
+    let expected = [
+        ("main", 1),
+        ("Down", 3),
+        ("Do (built-in)", 3),
+        ("If (built-in)", 3),
+        ("Def (built-in)", 3),
+        ("> (built-in)", 3),
+        ("- (built-in)", 2),
+        ("Def-fn (built-in)", 1),
+    ];
+    assert_eq!(
+        calls,
+        HashMap::from(expected.map(|(n, c)| (n.to_string(), c)))
+    );
+    assert!(stdout.contains("peak call depth: 7\n"), "{stdout}");
+    assert!(stdout.contains("peak data stack: 3\n"), "{stdout}");
+
+    let folded = std::fs::read_to_string(&folded).unwrap();
+    let paths: Vec<_> = folded
+        .lines()
+        .map(|line| {
+            let (path, micros) = line.rsplit_once(' ').unwrap();
+            assert!(micros.parse::<u64>().is_ok(), "{line}");
+            path
+        })
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "main",
+            "main;Def-fn",
+            "main;Down",
+            "main;Down;Def",
+            "main;Down;Do",
+            "main;Down;Do;-",
+            "main;Down;Do;Down",
+            "main;Down;Do;Down;Def",
+            "main;Down;Do;Down;Do",
+            "main;Down;Do;Down;Do;-",
+            "main;Down;Do;Down;Do;Down",
+            "main;Down;Do;Down;Do;Down;Def",
+            "main;Down;Do;Down;Do;Down;Do",
+            "main;Down;Do;Down;Do;Down;If",
+            "main;Down;Do;Down;Do;Down;If;>",
+            "main;Down;Do;Down;If",
+            "main;Down;Do;Down;If;>",
+            "main;Down;If",
+            "main;Down;If;>",
+        ]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
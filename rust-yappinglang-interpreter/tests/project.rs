@@ -0,0 +1,131 @@
+use std::path::Path;
+use std::process::Command;
+
+fn yap(args: &[&Path]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+        .args(args)
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Standard error of a run that has to fail.
+fn yap_error(args: &[&Path]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+/// Uncompressed tar with one directory holding `files`.
+fn tar(dir: &str, files: &[(&str, &str)]) -> Vec<u8> {
+    let mut data = vec![];
+    for (name, content) in files {
+        let mut header = [0u8; 512];
+        let name = format!("{dir}/{name}");
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", content.len()).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        data.extend_from_slice(&header);
+        data.extend_from_slice(content.as_bytes());
+        data.resize(data.len().div_ceil(512) * 512, 0);
+    }
+    data.resize(data.len() + 1024, 0);
+    data
+}
+
+#[test]
+fn vendor_and_run_project() {
+    let dir = std::env::temp_dir().join(format!("yp-project-{}", std::process::id()));
+    for sub in ["app/lib", "utils", "core"] {
+        std::fs::create_dir_all(dir.join(sub)).unwrap();
+    }
+    std::fs::write(
+        dir.join("app").join("yap.toml"),
+        "[project]\nname = \"app\"\nentry = \"main.yp\"\nlibrary-path = [\"lib\"]\n\n\
+         [dependencies]\nutils = \"../utils\"\nshapes = \"../shapes.tar\" # archive\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("app").join("main.yp"),
+        "Import \"utils \"U. ;\nPrint U.Inc 1 ;\nImport \"shapes/square.yp \"S. ;\nPrint S.Square 3 ;\n\
+         Import \"core \"C. ;\nPrint C.Core ;\nDo-local Use-file \"local.yp ;\nPrint Local ;\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("app").join("lib").join("local.yp"),
+        "Def \"Local 5 ;\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("utils").join("yap.toml"),
+        "[project]\nentry = \"lib.yp\"\n\n[dependencies]\ncore = \"../core\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("utils").join("lib.yp"), "Def-fn \"Inc ( + 1 ) ;\n").unwrap();
+    std::fs::write(dir.join("core").join("main.yp"), "Def \"Core 42 ;\n").unwrap();
+    std::fs::write(
+        dir.join("shapes.tar"),
+        tar(
+            "shapes",
+            &[("square.yp", "Def-fn \"Square ( Def \"X ; * X X ) ;\n")],
+        ),
+    )
+    .unwrap();
+
+    let app = dir.join("app");
+    assert!(yap(&[&app]).is_empty(), "archive used before vendoring");
+    let stdout = yap(&[Path::new("vendor"), &app]);
+    assert!(stdout.contains("vendored shapes"), "{stdout}");
+    let lock = std::fs::read_to_string(app.join("yap.lock")).unwrap();
+    assert!(
+        lock.contains("[package.core]\nsource = \"../core\""),
+        "{lock}"
+    );
+    assert!(
+        lock.contains("[package.shapes]\nsource = \"../shapes.tar\""),
+        "{lock}"
+    );
+    assert!(lock.contains("dependencies = [\"core\"]"), "{lock}");
+    assert!(app
+        .join("vendor")
+        .join("shapes")
+        .join("square.yp")
+        .is_file());
+
+    let stdout = yap(&[&app]);
+    assert!(stdout.starts_with("2\n9\n42\n5\n"), "{stdout}");
+
+    let vendored = app.join("vendor").join("core").join("main.yp");
+    std::fs::write(&vendored, "Def \"Core 43 ;\n").unwrap();
+    let stderr = yap_error(&[&app]);
+    assert!(stderr.contains("does not match its checksum"), "{stderr}");
+    yap(&[Path::new("vendor"), &app]);
+    assert!(yap(&[&app]).starts_with("2\n9\n42\n5\n"));
+
+    let manifest = std::fs::read_to_string(app.join("yap.toml")).unwrap();
+    std::fs::write(
+        app.join("yap.toml"),
+        format!("{manifest}extra = \"../core\"\n"),
+    )
+    .unwrap();
+    let stderr = yap_error(&[&app]);
+    assert!(
+        stderr.contains("dependency 'extra' is not locked"),
+        "{stderr}"
+    );
+    std::fs::write(
+        app.join("yap.toml"),
+        manifest.replace("shapes = \"../shapes.tar\" # archive\n", ""),
+    )
+    .unwrap();
+    let stderr = yap_error(&[&app]);
+    assert!(
+        stderr.contains("package 'shapes' is no longer a dependency"),
+        "{stderr}"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
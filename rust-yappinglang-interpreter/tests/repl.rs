@@ -0,0 +1,64 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn repl(input: &str) -> (String, bool) {
+    // History is not written to a missing home.
+    repl_with_home(input, &std::env::temp_dir().join("yp-repl-missing-home"))
+}
+
+fn repl_with_home(input: &str, home: &Path) -> (String, bool) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+        .env("HOME", home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        output.status.success(),
+    )
+}
+
+#[test]
+fn errors_multi_line_and_eof() {
+    let (stdout, success) =
+        repl("Print + 1 2\n)\nDef \"X (\n  + 1 2\n) ;\nPrint Do X\nPrint \"\"a\nb\"\"\n");
+    assert!(success);
+    assert!(stdout.contains("to many )"), "{stdout}");
+    assert!(stdout.contains("> . . > 3\n"), "{stdout}");
+    assert!(stdout.contains("'a\nb'"), "{stdout}");
+}
+
+#[test]
+fn history_is_saved() {
+    let home = std::env::temp_dir().join(format!("yp-repl-home-{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    repl_with_home("Print 1\n\nPrint 1\nPrint 2\n", &home);
+    let history = std::fs::read_to_string(home.join(".yap_history")).unwrap();
+    assert_eq!(history, "Print 1\nPrint 2\n");
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn meta_commands() {
+    let (stdout, _) = repl(
+        "Def \"X 1\n+ 1 2\n:defs\n:type ( 1 )\n:type ( 1 ) Def \"Y 2\n:defs\n:stack\n:clear\n:stack\n:ast Print 1\n:reload\n:nope\n",
+    );
+    assert!(stdout.contains("= 3\n"), "{stdout}");
+    assert!(
+        stdout.contains(
+            "> X  Integer\n> Block\n> Block\n> X  Integer\n> 3\n> > > (block\n  (var Print :1)\n  (integer 1 :1))\n"
+        ),
+        "{stdout}"
+    );
+    assert!(stdout.contains("nothing loaded yet"), "{stdout}");
+    assert!(stdout.contains("unknown command :nope"), "{stdout}");
+}
@@ -0,0 +1,49 @@
+use std::process::Command;
+
+#[test]
+fn reports_failures_and_exits_non_zero() {
+    let dir = std::env::temp_dir().join(format!("yp-test-command-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(
+        dir.join("nested").join("sum_test.yp"),
+        "Test \"passes (Assert-eq 3 + 1 2);\nTest \"fails (Assert-eq 4 + 1 2);\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("nested").join("once_test.yp"),
+        "Print \"loaded ;\nDef \"N 1 ;\nTest \"defines (Def \"N 2 ; Assert-eq 2 N);\n\
+         Test \"sees-top-level (Assert-eq 1 N ; Assert-eq List ( ) List-stack);\n\
+         Def-fn \"Double (* 2) ;\nTest \"redefines (Def-fn \"Double (* 3) ; Assert-eq 6 Double 2 ; 7);\n\
+         Test \"relies-on-original (Assert-eq 4 Double 2 ; Assert-eq List ( ) List-stack);\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("helper.yp"), "Test \"not-collected (Assert 0);\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+        .arg("test")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("ok    passes"), "{stdout}");
+    assert!(stdout.contains("FAIL  fails"), "{stdout}");
+    assert!(stdout.contains("expected 4, found 3"), "{stdout}");
+    assert!(!stdout.contains("not-collected"), "{stdout}");
+    assert_eq!(stdout.matches("'loaded'").count(), 1, "{stdout}");
+    assert!(stdout.contains("ok    sees-top-level"), "{stdout}");
+    assert!(stdout.contains("ok    relies-on-original"), "{stdout}");
+    assert!(stdout.contains("5 passed; 1 failed"), "{stdout}");
+    assert_eq!(output.status.code(), Some(1));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn examples_pass() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples");
+    let status = Command::new(env!("CARGO_BIN_EXE_rust-yappinglang-interpreter"))
+        .arg("test")
+        .arg(examples)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}